@@ -0,0 +1,129 @@
+//! Suggestions for the word being typed in a program, for editors to offer as completions.
+
+use std::ops::Range;
+
+use crate::keywords::{self, Keyword};
+use crate::tokens::{self, TokenKind};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    Mnemonic,
+    Directive,
+    Label,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    /// The text that replaces the word being typed.
+    pub text: String,
+    pub kind: CompletionKind,
+    /// A short description to show next to the suggestion.
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Completions {
+    /// Byte range of the word being typed, which a completion replaces. It doesn't include the
+    /// `/` of a label reference.
+    pub span: Range<usize>,
+    pub items: Vec<Completion>,
+}
+
+/// Suggests how to complete the word that ends at byte `offset` of `source`. Mnemonics and
+/// directives are suggested where a command is expected and labels after a `/`, using the
+/// labels defined anywhere in the source, even if it doesn't assemble.
+pub fn complete(source: &str, offset: usize) -> Completions {
+    let line_start = source[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+    let line = &source[line_start..offset];
+    let word_start = line.rfind([' ', '\t']).map_or(0, |idx| idx + 1);
+    let word = &line[word_start..];
+    let span = |start: usize| line_start + start..offset;
+
+    // Nothing is expected in comments, and the first word of a line is a new label.
+    if line.contains(';') || word_start == 0 {
+        return Completions::default();
+    }
+
+    if let Some(prefix) = word.strip_prefix('/') {
+        let mut labels: Vec<&str> = tokens::tokenize(source)
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::LabelDefinition)
+            .map(|token| &source[token.span])
+            .filter(|label| label.starts_with(prefix) && *label != prefix)
+            .collect();
+        labels.sort_unstable();
+        labels.dedup();
+        return Completions {
+            span: span(word_start + 1),
+            items: labels
+                .into_iter()
+                .map(|label| Completion {
+                    text: label.to_string(),
+                    kind: CompletionKind::Label,
+                    detail: "Label".to_string(),
+                })
+                .collect(),
+        };
+    }
+
+    // The command is the first word after the label, if there is one.
+    let is_command = !line[..word_start].trim().contains([' ', '\t']);
+    if !is_command {
+        return Completions::default();
+    }
+    let lowercase = !word.is_empty() && word.chars().all(|c| c.is_ascii_lowercase());
+    Completions {
+        span: span(word_start),
+        items: keywords::KEYWORDS
+            .iter()
+            .filter(|keyword| {
+                keyword.name.len() > word.len()
+                    && keyword.name[..word.len()].eq_ignore_ascii_case(word)
+            })
+            .map(|keyword| keyword_completion(keyword, lowercase))
+            .collect(),
+    }
+}
+
+fn keyword_completion(keyword: &Keyword, lowercase: bool) -> Completion {
+    Completion {
+        text: if lowercase {
+            keyword.name.to_lowercase()
+        } else {
+            keyword.name.to_string()
+        },
+        kind: if keyword.opcode.is_some() {
+            CompletionKind::Mnemonic
+        } else {
+            CompletionKind::Directive
+        },
+        detail: keyword.syntax.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn complete_words() {
+    let texts = |source: &str| -> Vec<String> {
+        complete(source, source.len())
+            .items
+            .into_iter()
+            .map(|item| item.text)
+            .collect()
+    };
+    assert_eq!(texts("        L"), ["LD"]);
+    assert_eq!(texts("loop    d"), ["dec", "data"]);
+    assert_eq!(texts("        H"), ["HALT"]);
+    assert_eq!(texts("        HALT"), Vec::<String>::new());
+    assert_eq!(texts("        ").len(), 12);
+    assert_eq!(texts("LOO"), Vec::<String>::new());
+    assert_eq!(texts("        LD /1 ; L"), Vec::<String>::new());
+
+    let program = "loop    BR /loop\nlast    DATA 2\nx       DATA 1\n        LD /l";
+    assert_eq!(texts(program), ["last", "loop"]);
+    assert_eq!(
+        complete(program, program.len()).span,
+        program.len() - 1..program.len()
+    );
+    assert_eq!(texts(&program[..program.len() - 1]).len(), 3);
+}
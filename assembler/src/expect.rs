@@ -0,0 +1,126 @@
+//! Assertions a program makes about itself in comments, so that exercises can check their own
+//! solutions. A comment such as `;@expect suma = 20, ACC = 0` states what the program must leave
+//! in memory and the accumulator when it halts. Words are named by label or by address such as
+//! `/12`, and values can be negative, in which case they are compared in two's complement.
+
+use simplez_common::*;
+use twelve_bit::u12::U12;
+
+use crate::tokens::{self, TokenKind};
+use crate::{line_number, Error, ErrorKind};
+
+/// Records the `;@expect` assertions of `source` in `debug_info`, which must already know the
+/// labels of the program.
+pub(crate) fn record_assertions<'s>(
+    source: &'s str,
+    debug_info: &mut DebugInfo,
+) -> Result<(), Error<&'s str>> {
+    for token in tokens::tokenize(source) {
+        if token.kind != TokenKind::Comment {
+            continue;
+        }
+        let comment = source[token.span][1..].trim_start();
+        let list = match comment.strip_prefix("@expect") {
+            Some(list) if list.is_empty() || list.starts_with(char::is_whitespace) => list,
+            _ => continue,
+        };
+        let line = line_number(source, list);
+        for assertion in list.split(',') {
+            let invalid = || Error {
+                input: assertion,
+                kind: ErrorKind::InvalidAssertion,
+            };
+            let (name, value) = assertion.split_once('=').ok_or_else(invalid)?;
+            let name = name.trim();
+            let expected = parse_value(value.trim()).ok_or_else(invalid)?;
+            let target = if name.eq_ignore_ascii_case("acc") {
+                AssertionTarget::Acc
+            } else {
+                AssertionTarget::Word(resolve(name, debug_info)?)
+            };
+            debug_info.add_assertion(Assertion {
+                name: name.to_string(),
+                target,
+                expected,
+                line,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn resolve<'s>(name: &'s str, debug_info: &DebugInfo) -> Result<Address, Error<&'s str>> {
+    let label = name.strip_prefix('/').unwrap_or(name);
+    match label.parse::<u16>() {
+        Ok(address) if address < 512 => Ok(Address(U12::from_u16(address))),
+        Ok(_) => Err(Error {
+            input: name,
+            kind: ErrorKind::InvalidAssertion,
+        }),
+        Err(_) => debug_info
+            .symbol(label)
+            .map(|symbol| symbol.address)
+            .ok_or_else(|| Error {
+                input: name,
+                kind: ErrorKind::UndefinedLabel {
+                    name: label.to_string(),
+                },
+            }),
+    }
+}
+
+fn parse_value(value: &str) -> Option<U12> {
+    let number = value.parse::<i32>().ok()?;
+    (-2048..=4095)
+        .contains(&number)
+        .then(|| U12::from_u16((number & 0o7777) as u16))
+}
+
+#[cfg(test)]
+#[test]
+fn records_assertions() {
+    let (_, debug_info) = crate::assemble_with_debug_info(
+        "        LD /x ;@expect x = -1, /3 = 7\n        HALT ; @expect acc = 4095\nx       DATA 4095\n",
+    )
+    .unwrap();
+    let targets: Vec<_> = debug_info
+        .assertions()
+        .iter()
+        .map(|assertion| {
+            (
+                assertion.name.as_str(),
+                assertion.target,
+                u16::from(assertion.expected),
+                assertion.line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        targets,
+        vec![
+            (
+                "x",
+                AssertionTarget::Word(Address(U12::from_u16(2))),
+                4095,
+                0
+            ),
+            ("/3", AssertionTarget::Word(Address(U12::from_u16(3))), 7, 0),
+            ("acc", AssertionTarget::Acc, 4095, 1),
+        ]
+    );
+
+    for wrong in ["x = 5000", "x 1", "/600 = 1"] {
+        let source = format!("        HALT ;@expect {}\nx       DATA 0\n", wrong);
+        let err = crate::assemble_with_debug_info(&source).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidAssertion));
+    }
+    let err = crate::assemble_with_debug_info("        HALT ;@expect y = 1\n").unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::UndefinedLabel { name } if name == "y"));
+
+    let (_, debug_info) = crate::assemble_with_debug_info(
+        "        HALT ;@expectation: it halts
+",
+    )
+    .unwrap();
+    assert!(debug_info.assertions().is_empty());
+}
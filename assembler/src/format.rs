@@ -0,0 +1,204 @@
+//! Reformats programs so that their labels, mnemonics, parameters and comments line up.
+
+use crate::keywords;
+use crate::parse_assembly_line;
+use crate::tokens;
+
+/// The case mnemonics and directives are written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MnemonicCase {
+    #[default]
+    Upper,
+    Lower,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The mnemonic and comment columns start at a multiple of this many columns.
+    pub tab_width: usize,
+    pub case: MnemonicCase,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 8,
+            case: MnemonicCase::Upper,
+        }
+    }
+}
+
+/// A line of code split into its columns.
+struct Columns<'s> {
+    label: &'s str,
+    command: String,
+    parameters: String,
+    comment: &'s str,
+}
+
+enum Line<'s> {
+    Blank,
+    /// A line with only a comment. `indented` tells whether it starts in the command column.
+    Comment {
+        text: &'s str,
+        indented: bool,
+    },
+    Code(Columns<'s>),
+    /// A line that doesn't parse, which is kept as it was.
+    Verbatim(&'s str),
+}
+
+/// Reformats `source` with every column aligned, using spaces. Mnemonics and directives are
+/// written in `options.case` and parameters of instructions that take an address get their `/`.
+/// Comments and blank lines are kept, and lines that don't parse are left untouched.
+pub fn format(source: &str, options: FormatOptions) -> String {
+    let lines: Vec<Line> = source
+        .split('\n')
+        .map(|line| parse_line(line.strip_suffix('\r').unwrap_or(line), options.case))
+        .collect();
+
+    let code = || {
+        lines.iter().filter_map(|line| match line {
+            Line::Code(columns) => Some(columns),
+            _ => None,
+        })
+    };
+    let tab_width = options.tab_width.max(1);
+    let round_up = |width: usize| width + (tab_width - width % tab_width) % tab_width;
+    let label_width = round_up(code().map(|c| c.label.len() + 1).max().unwrap_or(1));
+    let command_width = code().map(|c| c.command.len() + 1).max().unwrap_or(0);
+    let comment_column = round_up(
+        label_width
+            + command_width
+            + code()
+                .filter(|c| !c.comment.is_empty())
+                .map(|c| c.parameters.len() + 1)
+                .max()
+                .unwrap_or(0),
+    );
+
+    let formatted: Vec<String> = lines
+        .into_iter()
+        .map(|line| match line {
+            Line::Blank => String::new(),
+            Line::Comment { text, indented } if indented => {
+                format!("{:label_width$}{}", "", text)
+            }
+            Line::Comment { text, .. } => text.to_string(),
+            Line::Code(columns) => {
+                let code = format!(
+                    "{:label_width$}{:command_width$}{}",
+                    columns.label, columns.command, columns.parameters
+                );
+                if columns.comment.is_empty() {
+                    code.trim_end().to_string()
+                } else {
+                    format!("{:comment_column$}{}", code, columns.comment)
+                }
+            }
+            Line::Verbatim(text) => text.to_string(),
+        })
+        .collect();
+    formatted.join("\n")
+}
+
+fn parse_line(line: &str, case: MnemonicCase) -> Line<'_> {
+    let code_end = line.find(';').unwrap_or(line.len());
+    let (code, comment) = line.split_at(code_end);
+    let comment = comment.trim_end();
+    if code.trim().is_empty() {
+        return if comment.is_empty() {
+            Line::Blank
+        } else {
+            Line::Comment {
+                text: comment,
+                indented: code_end > 0,
+            }
+        };
+    }
+
+    let mut words = tokens::words(code).map(|(_, word)| word);
+    let label = if line.starts_with(|c: char| !c.is_whitespace()) {
+        words.next().unwrap_or_default()
+    } else {
+        ""
+    };
+    let command = words.next().unwrap_or_default();
+    let keyword = keywords::keyword(command);
+    let parameters: Vec<String> = words
+        .map(|word| match keyword {
+            Some(keyword)
+                if keyword.takes_address()
+                    && (tokens::is_label(word) || tokens::is_number(word)) =>
+            {
+                format!("/{}", word)
+            }
+            _ => word.to_string(),
+        })
+        .collect();
+    let columns = Columns {
+        label,
+        command: match (keyword, case) {
+            (Some(keyword), MnemonicCase::Upper) => keyword.name.to_string(),
+            (Some(keyword), MnemonicCase::Lower) => keyword.name.to_lowercase(),
+            (None, _) => command.to_string(),
+        },
+        parameters: parameters.join(" "),
+        comment,
+    };
+
+    // Spacing doesn't change how a line parses, so the columns are checked as they are written
+    // without alignment.
+    let unaligned = [
+        columns.label,
+        &columns.command,
+        &columns.parameters,
+        columns.comment,
+    ]
+    .join(" ");
+    match parse_assembly_line(unaligned.trim_end()) {
+        Ok(("", _)) => Line::Code(columns),
+        _ => Line::Verbatim(line),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn format_columns() {
+    let source = "\torg 0\n\
+                  \n\
+                  ; Sums two numbers\n\
+                  start ld a ; first\n  \t  ADD /b\n\
+                  \x20 st /sum   ; result\n\
+                  \x20 HaLt\n\
+                  a data 3\n\
+                  b   data 4\n\
+                  sum res 1\n\
+                  \x20     ; the end\n\
+                  bad lod /a\n";
+    let expected = "        ORG  0\n\
+                    \n\
+                    ; Sums two numbers\n\
+                    start   LD   /a         ; first\n\
+                    \x20       ADD  /b\n\
+                    \x20       ST   /sum       ; result\n\
+                    \x20       HALT\n\
+                    a       DATA 3\n\
+                    b       DATA 4\n\
+                    sum     RES  1\n\
+                    \x20       ; the end\n\
+                    bad lod /a\n";
+    let options = FormatOptions::default();
+    let formatted = format(source, options);
+    assert_eq!(formatted, expected);
+    assert_eq!(format(&formatted, options), formatted);
+
+    let lower = format(
+        "x data 1\r\n  br /x",
+        FormatOptions {
+            tab_width: 4,
+            case: MnemonicCase::Lower,
+        },
+    );
+    assert_eq!(lower, "x   data 1\n    br   /x");
+}
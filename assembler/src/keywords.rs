@@ -0,0 +1,135 @@
+//! Reference documentation for every mnemonic and directive, for editors to show as help.
+
+/// Describes an instruction mnemonic or a directive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keyword {
+    /// The name of the keyword, in upper case.
+    pub name: &'static str,
+    /// How the keyword is written, with its parameter if it takes one.
+    pub syntax: &'static str,
+    /// The three-bit operation code of an instruction, or `None` for directives.
+    pub opcode: Option<u16>,
+    /// What the keyword does.
+    pub summary: &'static str,
+}
+
+impl Keyword {
+    /// Describes how an instruction is encoded in a word, such as `0o1000 + d` for `LD /d`.
+    pub fn encoding(&self) -> Option<String> {
+        let opcode = self.opcode?;
+        Some(if self.takes_address() {
+            format!("{:#o} + d", opcode << 9)
+        } else {
+            format!("{:#o}", opcode << 9)
+        })
+    }
+
+    /// Whether the keyword is followed by an address parameter such as `/12` or `/loop`.
+    pub fn takes_address(&self) -> bool {
+        self.syntax.ends_with("/d")
+    }
+}
+
+/// Every mnemonic, ordered by opcode, followed by every directive.
+pub const KEYWORDS: [Keyword; 12] = [
+    Keyword {
+        name: "ST",
+        syntax: "ST /d",
+        opcode: Some(0),
+        summary: "Stores the accumulator at address d: MEM[d] ← ACC",
+    },
+    Keyword {
+        name: "LD",
+        syntax: "LD /d",
+        opcode: Some(1),
+        summary: "Loads the word at address d into the accumulator: ACC ← MEM[d]",
+    },
+    Keyword {
+        name: "ADD",
+        syntax: "ADD /d",
+        opcode: Some(2),
+        summary: "Adds the word at address d to the accumulator: ACC ← ACC + MEM[d]",
+    },
+    Keyword {
+        name: "BR",
+        syntax: "BR /d",
+        opcode: Some(3),
+        summary: "Branches to address d: PC ← d",
+    },
+    Keyword {
+        name: "BZ",
+        syntax: "BZ /d",
+        opcode: Some(4),
+        summary: "Branches to address d if the accumulator is zero: if ACC = 0 then PC ← d",
+    },
+    Keyword {
+        name: "CLR",
+        syntax: "CLR",
+        opcode: Some(5),
+        summary: "Clears the accumulator: ACC ← 0",
+    },
+    Keyword {
+        name: "DEC",
+        syntax: "DEC",
+        opcode: Some(6),
+        summary: "Decrements the accumulator: ACC ← ACC - 1",
+    },
+    Keyword {
+        name: "HALT",
+        syntax: "HALT",
+        opcode: Some(7),
+        summary: "Stops the machine",
+    },
+    Keyword {
+        name: "ORG",
+        syntax: "ORG n",
+        opcode: None,
+        summary: "Assembles the statements that follow starting at address n",
+    },
+    Keyword {
+        name: "DATA",
+        syntax: "DATA n",
+        opcode: None,
+        summary: "Stores the number n in the next word",
+    },
+    Keyword {
+        name: "RES",
+        syntax: "RES n",
+        opcode: None,
+        summary: "Reserves the next n words, leaving them zeroed",
+    },
+    Keyword {
+        name: "END",
+        syntax: "END",
+        opcode: None,
+        summary: "Ends the program; the lines after it are not assembled",
+    },
+];
+
+/// Looks up a mnemonic or directive by name, ignoring case.
+pub fn keyword(name: &str) -> Option<&'static Keyword> {
+    KEYWORDS
+        .iter()
+        .find(|keyword| keyword.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+#[test]
+fn keywords_match_the_parser() {
+    use crate::{DIRECTIVES, MNEMONICS};
+
+    let names: Vec<&str> = KEYWORDS.iter().map(|keyword| keyword.name).collect();
+    assert_eq!(names, [&MNEMONICS[..], &DIRECTIVES[..]].concat());
+    for keyword in &KEYWORDS[..8] {
+        let instruction = if keyword.takes_address() {
+            format!("{} /3", keyword.name)
+        } else {
+            keyword.name.to_string()
+        };
+        let word = u16::from(crate::assemble_instruction(&instruction).unwrap());
+        assert_eq!(Some(word >> 9), keyword.opcode);
+    }
+    assert_eq!(keyword("ld").unwrap().encoding().unwrap(), "0o1000 + d");
+    assert_eq!(keyword("halt").unwrap().encoding().unwrap(), "0o7000");
+    assert_eq!(keyword("data").unwrap().encoding(), None);
+}
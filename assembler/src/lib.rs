@@ -6,7 +6,7 @@ use nom::character::complete::{
     alpha1, alphanumeric0, alphanumeric1, digit1, newline, space0, space1,
 };
 use nom::character::is_alphabetic;
-use nom::combinator::{map, map_res, opt};
+use nom::combinator::{consumed, map, map_res, opt};
 use nom::error::{FromExternalError, ParseError};
 use nom::multi::{many0, many1, separated_list0, separated_list1};
 use nom::sequence::{pair, preceded, terminated, tuple};
@@ -17,6 +17,18 @@ pub use nom;
 use twelve_bit::u12;
 use twelve_bit::u12::*;
 
+pub mod completion;
+mod expect;
+pub mod format;
+pub mod keywords;
+pub mod lint;
+pub mod tokens;
+
+/// The names of every instruction, as written in assembly. They are case insensitive.
+pub const MNEMONICS: [&str; 8] = ["ST", "LD", "ADD", "BR", "BZ", "CLR", "DEC", "HALT"];
+/// The names of every directive, as written in assembly. They are case insensitive.
+pub const DIRECTIVES: [&str; 4] = ["ORG", "DATA", "RES", "END"];
+
 #[derive(Copy, Clone, Debug)]
 pub enum ParamType {
     Direction,
@@ -32,16 +44,65 @@ pub enum ErrorKind {
     RedefinedLabel { name: String },
     MissingParameter,
     InvalidLabelName,
+    InvalidAssertion,
+    PastEndOfMemory,
     SyntaxError,
     ParseError(nom::error::ErrorKind),
 }
 
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::InvalidParameter {
+                expected_type: ParamType::Direction,
+            } => f.write_str("Expected an address such as /12 or /label"),
+            ErrorKind::InvalidParameter {
+                expected_type: ParamType::Number,
+            } => f.write_str("Expected a number"),
+            ErrorKind::InvalidNumber => f.write_str("Invalid number"),
+            ErrorKind::UndefinedLabel { name } => {
+                f.write_fmt(format_args!("Undefined label `{}`", name))
+            }
+            ErrorKind::InvalidInstruction { name } => {
+                f.write_fmt(format_args!("Unknown instruction `{}`", name))
+            }
+            ErrorKind::RedefinedLabel { name } => {
+                f.write_fmt(format_args!("Label `{}` is already defined", name))
+            }
+            ErrorKind::MissingParameter => f.write_str("Missing parameter"),
+            ErrorKind::InvalidLabelName => f.write_str("Invalid label name"),
+            ErrorKind::InvalidAssertion => {
+                f.write_str("Expected assertions such as `;@expect suma = 20, ACC = 0`")
+            }
+            ErrorKind::PastEndOfMemory => f.write_str("There is no memory past /511"),
+            ErrorKind::SyntaxError => f.write_str("Syntax error"),
+            ErrorKind::ParseError(kind) => {
+                f.write_fmt(format_args!("Syntax error ({})", kind.description()))
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Error<I> {
     pub input: I,
     pub kind: ErrorKind,
 }
 
+impl Error<&str> {
+    /// Returns the byte range of `source` the error is about: the word its input starts with.
+    /// `source` must be the program that was assembled.
+    pub fn span(&self, source: &str) -> std::ops::Range<usize> {
+        let start = (self.input.as_ptr() as usize)
+            .saturating_sub(source.as_ptr() as usize)
+            .min(source.len());
+        let len = source[start..]
+            .find(|c: char| c.is_whitespace() || c == ';')
+            .unwrap_or(source.len() - start);
+        start..start + len
+    }
+}
+
 impl<I> ParseError<I> for Error<I> {
     fn from_error_kind(input: I, kind: nom::error::ErrorKind) -> Self {
         Self {
@@ -113,20 +174,29 @@ pub fn parse_direction<'s>(input: &'s str) -> IResult<&str, Direction<'s>, Error
         tag("/"),
         alt((
             map(parse_label, |label| Direction::Label(label)),
-            map(
-                pair(opt(tag("-")), digit1),
-                |(sign, addr): (Option<&str>, &str)| {
-                    if let Some(_sign) = sign {
-                        Direction::Address(Address(u12!(4096) - addr.parse::<U12>().unwrap()))
-                    } else {
-                        Direction::Address(Address(addr.parse::<U12>().unwrap()))
-                    }
-                },
-            ),
+            map(parse_address, Direction::Address),
         )),
     )(input)
 }
 
+/// Parses the digits of an address such as the `12` of `/12`, or `-1` for the last word.
+fn parse_address(input: &str) -> IResult<&str, Address, Error<&str>> {
+    let (rest, (sign, digits)) = pair(opt(tag("-")), digit1)(input)?;
+    // Digits that don't fit in a word can't be anything else, so this is a failure rather than
+    // an error that lets other parsers try.
+    let addr = digits.parse::<U12>().map_err(|_| {
+        nom::Err::Failure(Error {
+            input: digits,
+            kind: ErrorKind::InvalidNumber,
+        })
+    })?;
+    if let Some(_sign) = sign {
+        Ok((rest, Address(u12!(4096) - addr)))
+    } else {
+        Ok((rest, Address(addr)))
+    }
+}
+
 pub fn parse_parameter<'s>(input: &'s str) -> IResult<&str, Parameter<'s>, Error<&str>> {
     let dir_parser = map(parse_direction, Parameter::Direction);
     let num_parser = map_res(digit1, |num| {
@@ -138,23 +208,14 @@ pub fn parse_parameter<'s>(input: &'s str) -> IResult<&str, Parameter<'s>, Error
     alt((dir_parser, num_parser))(input)
 }
 
-pub fn parse_assembly_line<'s>(input: &'s str) -> IResult<&str, AssemblyLine<'s>, Error<&str>> {
-    let (input, (label, _, instruction, _)) = terminated(
-        tuple((
-            alphanumeric0,
-            space1,
-            opt(tuple((
-                alpha1,
-                opt(preceded(space1, separated_list1(space1, parse_parameter))),
-            ))),
-            opt(tuple((space0, tag(";"), many0(is_not("\n"))))),
-        )),
-        space0,
-    )(input)?;
-
-    let label = if label.is_empty() { None } else { Some(label) };
+/// Parses an instruction or directive along with its parameters, such as `LD /12` or `DATA 5`.
+pub fn parse_command<'s>(input: &'s str) -> IResult<&'s str, Command<'s>, Error<&'s str>> {
+    let (rest, (instruction, params)) = tuple((
+        alpha1,
+        opt(preceded(space1, separated_list1(space1, parse_parameter))),
+    ))(input)?;
 
-    let instruction = instruction.map(|(instruction, params)| -> Result<Command, Error<&'s str>> {
+    let command = (|| -> Result<Command, Error<&'s str>> {
         let get_number = || match params.as_ref().map(|p| p[0]).ok_or(Error {
             input,
             kind: ErrorKind::MissingParameter,
@@ -223,11 +284,24 @@ pub fn parse_assembly_line<'s>(input: &'s str) -> IResult<&str, AssemblyLine<'s>
                 })
             }
         })
-    });
-    let instruction = match instruction {
-        Some(x) => Some(x.map_err(|err| nom::Err::Failure(err))?),
-        None => None,
-    };
+    })()
+    .map_err(nom::Err::Failure)?;
+
+    Ok((rest, command))
+}
+
+pub fn parse_assembly_line<'s>(input: &'s str) -> IResult<&str, AssemblyLine<'s>, Error<&str>> {
+    let (input, (label, _, instruction, _)) = terminated(
+        tuple((
+            alphanumeric0,
+            space1,
+            opt(parse_command),
+            opt(tuple((space0, tag(";"), many0(is_not("\n"))))),
+        )),
+        space0,
+    )(input)?;
+
+    let label = if label.is_empty() { None } else { Some(label) };
 
     Ok((
         input,
@@ -239,16 +313,18 @@ pub fn parse_assembly_line<'s>(input: &'s str) -> IResult<&str, AssemblyLine<'s>
 }
 
 pub fn assemble<'s>(input: &'s str) -> Result<Memory, Error<&str>> {
+    assemble_with_debug_info(input).map(|(memory, _)| memory)
+}
+
+/// Assembles a program like [`assemble`], also returning information about where each word of
+/// memory came from.
+pub fn assemble_with_debug_info<'s>(input: &'s str) -> Result<(Memory, DebugInfo), Error<&'s str>> {
     let mut labels = HashMap::new();
     let lines = preceded(
         many0(newline),
-        separated_list0(many1(newline), parse_assembly_line),
+        separated_list0(many1(newline), consumed(parse_assembly_line)),
     )(input)
-    .map_err(|err| match err {
-        nom::Err::Error(x) => x,
-        nom::Err::Failure(x) => x,
-        nom::Err::Incomplete(_) => unreachable!(),
-    })?;
+    .map_err(into_error)?;
     let lines = if !lines.0.trim().is_empty() {
         return Err(Error {
             input: lines.0,
@@ -257,19 +333,25 @@ pub fn assemble<'s>(input: &'s str) -> Result<Memory, Error<&str>> {
     } else {
         lines.1
     };
+    let mut debug_info = DebugInfo::default();
     {
         let mut current_addr = Address::default();
-        for line in lines.iter() {
+        for (source, line) in lines.iter() {
             if let Some(label) = line.label {
                 if labels.contains_key(&label) {
                     return Err(Error {
-                        input,
+                        input: label,
                         kind: ErrorKind::RedefinedLabel {
                             name: label.to_string(),
                         },
                     });
                 }
                 labels.insert(label, current_addr);
+                debug_info.add_symbol(Symbol {
+                    name: label.to_string(),
+                    address: current_addr,
+                    line: line_number(input, source),
+                });
             }
             match line.command {
                 Some(Command::Directive(Directive::Org { address })) => current_addr = address,
@@ -279,12 +361,13 @@ pub fn assemble<'s>(input: &'s str) -> Result<Memory, Error<&str>> {
             }
         }
     }
+    expect::record_assertions(input, &mut debug_info)?;
 
-    let convert_direction = |dir: Direction| -> Result<Address, Error<&str>> {
+    let convert_direction = |dir: Direction<'s>| -> Result<Address, Error<&'s str>> {
         match dir {
             Direction::Address(addr) => Ok(addr),
             Direction::Label(label) => labels.get(&label).copied().ok_or_else(|| Error {
-                input,
+                input: label,
                 kind: ErrorKind::UndefinedLabel {
                     name: label.to_string(),
                 },
@@ -295,42 +378,104 @@ pub fn assemble<'s>(input: &'s str) -> Result<Memory, Error<&str>> {
     let mut memory = Memory::default();
     let mut current_addr = Address::default();
 
-    for command in lines.into_iter().filter_map(|line| line.command) {
+    for (source, command) in lines
+        .into_iter()
+        .filter_map(|(source, line)| Some((source, line.command?)))
+    {
+        let line = line_number(input, source);
+        let writes_word = matches!(
+            command,
+            Command::Instruction(_) | Command::Directive(Directive::Data { .. })
+        );
+        if writes_word && usize::from(u16::from(current_addr.0)) >= memory.0.len() {
+            return Err(Error {
+                input: source.trim_start(),
+                kind: ErrorKind::PastEndOfMemory,
+            });
+        }
         match command {
             Command::Instruction(instruction) => {
-                memory[current_addr] = match instruction {
-                    Instruction::Store { address } => convert_direction(address)?.0 & u12!(0o777),
-                    Instruction::Load { address } => {
-                        u12!(1 << 9) | convert_direction(address)?.0 & u12!(0o777)
-                    }
-                    Instruction::Add { address } => {
-                        u12!(2 << 9) | convert_direction(address)?.0 & u12!(0o777)
-                    }
-                    Instruction::Branch { address } => {
-                        u12!(3 << 9) | convert_direction(address)?.0 & u12!(0o777)
-                    }
-                    Instruction::BranchIfZero { address } => {
-                        u12!(4 << 9) | convert_direction(address)?.0 & u12!(0o777)
-                    }
-                    Instruction::Clear => u12!(5 << 9),
-                    Instruction::Decrease => u12!(6 << 9),
-                    Instruction::Halt => u12!(7 << 9),
-                };
+                memory[current_addr] = U12::from(instruction.try_map_address(convert_direction)?);
+                debug_info.set_word_kind(current_addr, WordKind::Instruction);
+                debug_info.set_source_line(current_addr, line);
                 current_addr.0 += u12!(1);
             }
             Command::Directive(directive) => match directive {
                 Directive::Org { address } => current_addr = address,
                 Directive::Data { value } => {
                     memory[current_addr] = value;
+                    debug_info.set_word_kind(current_addr, WordKind::Data);
+                    debug_info.set_source_line(current_addr, line);
                     current_addr.0 += u12!(1);
                 }
-                Directive::Reserve { amount } => current_addr.0 += amount,
-                Directive::End => return Ok(memory),
+                Directive::Reserve { amount } => {
+                    let start = u16::from(current_addr.0);
+                    let end = (start + u16::from(amount)).min(memory.0.len() as u16);
+                    for addr in start..end {
+                        let addr = Address(U12::from_u16(addr));
+                        debug_info.set_word_kind(addr, WordKind::Reserved);
+                        debug_info.set_source_line(addr, line);
+                    }
+                    current_addr.0 += amount;
+                }
+                Directive::End => return Ok((memory, debug_info)),
             },
         }
     }
 
-    Ok(memory)
+    Ok((memory, debug_info))
+}
+
+/// Assembles a single instruction such as `LD /12` into the word that encodes it. There is no
+/// program to look labels up in, so they are always reported as undefined.
+pub fn assemble_instruction(input: &str) -> Result<U12, Error<&str>> {
+    let (rest, command) = parse_command(input.trim()).map_err(into_error)?;
+    if !rest.trim().is_empty() {
+        return Err(Error {
+            input: rest,
+            kind: ErrorKind::SyntaxError,
+        });
+    }
+
+    match command {
+        Command::Instruction(instruction) => {
+            Ok(U12::from(instruction.try_map_address(|dir| match dir {
+                Direction::Address(addr) => Ok(addr),
+                Direction::Label(label) => Err(Error {
+                    input,
+                    kind: ErrorKind::UndefinedLabel {
+                        name: label.to_string(),
+                    },
+                }),
+            })?))
+        }
+        Command::Directive(_) => Err(Error {
+            input,
+            kind: ErrorKind::InvalidInstruction {
+                name: input.trim().to_lowercase(),
+            },
+        }),
+    }
+}
+
+/// Returns the zero-based line of `source` that `fragment` starts on. `fragment` must be a slice
+/// of `source`, such as the input of an [`Error`] returned when assembling it.
+pub fn line_number(source: &str, fragment: &str) -> usize {
+    let offset = (fragment.as_ptr() as usize)
+        .saturating_sub(source.as_ptr() as usize)
+        .min(source.len());
+    source.as_bytes()[..offset]
+        .iter()
+        .filter(|byte| **byte == b'\n')
+        .count()
+}
+
+fn into_error<I>(err: nom::Err<Error<I>>) -> Error<I> {
+    match err {
+        nom::Err::Error(x) => x,
+        nom::Err::Failure(x) => x,
+        nom::Err::Incomplete(_) => unreachable!(),
+    }
 }
 
 #[cfg(test)]
@@ -353,3 +498,85 @@ fn test() {
     // All lines with instructions should map 1:1 to words
     assert!(words.next() == None);
 }
+
+#[cfg(test)]
+#[test]
+fn assemble_single_instruction() {
+    assert_eq!(
+        u16::from(assemble_instruction("ld /12").unwrap()),
+        1 << 9 | 12
+    );
+    assert_eq!(u16::from(assemble_instruction(" HALT ").unwrap()), 7 << 9);
+    assert!(matches!(
+        assemble_instruction("br /loop").unwrap_err().kind,
+        ErrorKind::UndefinedLabel { .. }
+    ));
+    assert!(matches!(
+        assemble_instruction("data 5").unwrap_err().kind,
+        ErrorKind::InvalidInstruction { .. }
+    ));
+}
+
+#[cfg(test)]
+#[test]
+fn debug_info_maps_words_to_lines() {
+    let program = "        BR /START\n\nX       DATA 5\nY       RES 2\nSTART   HALT\n";
+    let (_, debug_info) = assemble_with_debug_info(program).unwrap();
+    let line = |addr: u16| debug_info.source_line(Address(U12::from_u16(addr)));
+    assert_eq!(line(0), Some(0));
+    assert_eq!(line(1), Some(2));
+    assert_eq!(line(2), Some(3));
+    assert_eq!(line(3), Some(3));
+    assert_eq!(line(4), Some(4));
+    assert_eq!(line(5), None);
+    assert_eq!(debug_info.statement_index(Address(u12!(4))), Some(3));
+
+    let start = debug_info.symbol("START").unwrap();
+    assert_eq!((start.address, start.line), (Address(u12!(4)), 4));
+    assert_eq!(debug_info.symbols().len(), 3);
+    assert!(debug_info.symbol("Z").is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn errors_point_at_their_word() {
+    let program = "        LD /X\n        BR /NOWHERE ; comment\nX       DATA 1\n";
+    let err = assemble(program).unwrap_err();
+    assert_eq!(&program[err.span(program)], "NOWHERE");
+    assert_eq!(line_number(program, err.input), 1);
+    assert_eq!(err.kind.to_string(), "Undefined label `NOWHERE`");
+
+    let program = "X       DATA 1\nX       FOO\n";
+    let err = assemble(program).unwrap_err();
+    assert_eq!(&program[err.span(program)], "FOO");
+}
+
+#[cfg(test)]
+#[test]
+fn words_past_the_end_of_memory() {
+    let program = "        ORG 600\n        HALT\n";
+    let err = assemble(program).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::PastEndOfMemory));
+    assert_eq!(&program[err.span(program)], "HALT");
+
+    let full = "        DATA 0\n".repeat(512);
+    assert!(assemble(&full).is_ok());
+    assert!(matches!(
+        assemble(&(full + "        DATA 0\n")).unwrap_err().kind,
+        ErrorKind::PastEndOfMemory
+    ));
+    assert!(assemble("        ORG 600\nX       RES 4\n").is_ok());
+}
+
+#[cfg(test)]
+#[test]
+fn addresses_too_large_for_a_word() {
+    let program = "        LD /99999\n        HALT\n";
+    let err = assemble(program).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::InvalidNumber));
+    assert_eq!(&program[err.span(program)], "99999");
+    assert!(matches!(
+        assemble_instruction("br /-99999").unwrap_err().kind,
+        ErrorKind::InvalidNumber
+    ));
+}
@@ -0,0 +1,452 @@
+//! Warnings about programs that assemble but are almost certainly wrong, found by looking at
+//! how control can flow through them.
+//!
+//! Lints can be turned off for a whole file with a comment such as
+//! `;@allow unused-label unreachable-code`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::ops::Range;
+
+use simplez_common::*;
+use twelve_bit::u12::U12;
+
+use crate::tokens::{self, Token, TokenKind};
+
+/// A kind of mistake the linter looks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    /// Execution can run into a word assembled from `DATA` or `RES`, or not assembled at all.
+    ExecutesData,
+    /// Instructions that execution never reaches.
+    UnreachableCode,
+    /// A label that no instruction refers to.
+    UnusedLabel,
+    /// A `RES` word read before anything is stored to it.
+    UninitializedRead,
+    /// A `BZ` that always or never branches, because the accumulator is known at that point.
+    ConstantBranch,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::ExecutesData,
+        Lint::UnreachableCode,
+        Lint::UnusedLabel,
+        Lint::UninitializedRead,
+        Lint::ConstantBranch,
+    ];
+
+    /// The name the lint is reported and configured with.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::ExecutesData => "executes-data",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::UnusedLabel => "unused-label",
+            Lint::UninitializedRead => "uninitialized-read",
+            Lint::ConstantBranch => "constant-branch",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Lint> {
+        Self::ALL.into_iter().find(|lint| lint.code() == code)
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub lint: Lint,
+    /// Byte range of the source the warning is about.
+    pub span: Range<usize>,
+    /// The zero-based line `span` starts on.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Which lints are checked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    allowed: BTreeSet<Lint>,
+}
+
+impl LintConfig {
+    /// Reads the lints a program turns off from its `;@allow` comments. Unknown lint codes are
+    /// ignored.
+    pub fn from_source(source: &str) -> Self {
+        let mut config = Self::default();
+        for token in tokens::tokenize(source) {
+            if token.kind != TokenKind::Comment {
+                continue;
+            }
+            let comment = source[token.span][1..].trim_start();
+            if let Some(codes) = comment.strip_prefix("@allow") {
+                codes
+                    .split([' ', '\t', ','])
+                    .filter_map(Lint::from_code)
+                    .for_each(|lint| config.allow(lint));
+            }
+        }
+        config
+    }
+
+    pub fn allow(&mut self, lint: Lint) {
+        self.allowed.insert(lint);
+    }
+
+    pub fn is_allowed(&self, lint: Lint) -> bool {
+        self.allowed.contains(&lint)
+    }
+}
+
+/// Assembles `source` and lints it with the configuration in its comments.
+pub fn lint(source: &str) -> Result<Vec<Warning>, crate::Error<&str>> {
+    let (memory, debug_info) = crate::assemble_with_debug_info(source)?;
+    Ok(lint_program(
+        source,
+        &memory,
+        &debug_info,
+        &LintConfig::from_source(source),
+    ))
+}
+
+/// Lints the program `source` was assembled into, returning the warnings in source order.
+pub fn lint_program(
+    source: &str,
+    memory: &Memory,
+    debug_info: &DebugInfo,
+    config: &LintConfig,
+) -> Vec<Warning> {
+    let mut linter = Linter {
+        source,
+        tokens: tokens::tokenize(source),
+        memory,
+        debug_info,
+        cfg: ControlFlowGraph::new(memory, debug_info),
+        warnings: Vec::new(),
+    };
+    linter.executes_data();
+    linter.unreachable_code();
+    linter.unused_labels();
+    linter.uninitialized_reads();
+    linter.constant_branches();
+
+    let mut warnings = linter.warnings;
+    warnings.retain(|warning| !config.is_allowed(warning.lint));
+    warnings.sort_by_key(|warning| (warning.span.start, warning.lint));
+    warnings
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    memory: &'a Memory,
+    debug_info: &'a DebugInfo,
+    cfg: ControlFlowGraph,
+    warnings: Vec<Warning>,
+}
+
+impl Linter<'_> {
+    fn executes_data(&mut self) {
+        for addr in self.cfg.reachable().collect::<Vec<_>>() {
+            let what = match self.debug_info.word_kind(addr) {
+                Some(WordKind::Instruction) => continue,
+                Some(WordKind::Data) => format!("the DATA word at {}", addr),
+                Some(WordKind::Reserved) => format!("the RES word at {}", addr),
+                None => format!("{}, which the program doesn't assemble to", addr),
+            };
+            let predecessors: Vec<Address> = self.cfg.predecessors(addr).collect();
+            if predecessors.is_empty() {
+                self.warn_at_word(
+                    Lint::ExecutesData,
+                    addr,
+                    format!("Execution starts at {}", what),
+                );
+            }
+            for pred in predecessors {
+                self.warn_at_word(
+                    Lint::ExecutesData,
+                    pred,
+                    format!("Execution can continue into {}", what),
+                );
+            }
+        }
+    }
+
+    fn unreachable_code(&mut self) {
+        let mut previous_unreachable = false;
+        for addr in addresses(self.memory) {
+            let unreachable = self.debug_info.word_kind(addr) == Some(WordKind::Instruction)
+                && !self.cfg.is_reachable(addr);
+            // Only the first instruction of a run of them is reported.
+            if unreachable && !previous_unreachable {
+                self.warn_at_word(
+                    Lint::UnreachableCode,
+                    addr,
+                    "This code is never executed".to_string(),
+                );
+            }
+            previous_unreachable = unreachable;
+        }
+    }
+
+    fn unused_labels(&mut self) {
+        let used: BTreeSet<&str> = self
+            .tokens
+            .iter()
+            .filter(|token| matches!(token.kind, TokenKind::LabelReference { .. }))
+            .filter_map(|token| token.label_name(self.source))
+            .collect();
+        let unused: Vec<Range<usize>> = self
+            .tokens
+            .iter()
+            .filter(|token| token.kind == TokenKind::LabelDefinition)
+            .filter(|token| !used.contains(&self.source[token.span.clone()]))
+            .map(|token| token.span.clone())
+            .collect();
+        for span in unused {
+            let message = format!("Label `{}` is never used", &self.source[span.clone()]);
+            self.warn(Lint::UnusedLabel, span, message);
+        }
+    }
+
+    /// Warns about `LD` and `ADD` instructions that can read a `RES` word before any `ST` has
+    /// written to it, on some path from the entry point.
+    fn uninitialized_reads(&mut self) {
+        let reserved: BTreeSet<Address> = addresses(self.memory)
+            .filter(|addr| self.debug_info.word_kind(*addr) == Some(WordKind::Reserved))
+            .collect();
+        let unwritten = self.dataflow(reserved, |unwritten, instruction| {
+            let mut unwritten = unwritten.clone();
+            if let Instruction::Store { address } = instruction {
+                unwritten.remove(&address);
+            }
+            unwritten
+        });
+
+        for (addr, unwritten) in unwritten {
+            let address = match Instruction::from(self.memory[addr]) {
+                Instruction::Load { address } | Instruction::Add { address } => address,
+                _ => continue,
+            };
+            if unwritten.contains(&address) {
+                let message = format!(
+                    "Reads {} before anything is stored to it",
+                    self.describe(address)
+                );
+                self.warn_at_word(Lint::UninitializedRead, addr, message);
+            }
+        }
+    }
+
+    /// Warns about `BZ` instructions that always see the same accumulator, following its value
+    /// through `CLR`, `DEC` and loads and additions of `DATA` words that are never stored to.
+    fn constant_branches(&mut self) {
+        let stored_to: BTreeSet<Address> = self
+            .cfg
+            .reachable()
+            .filter_map(|addr| match Instruction::from(self.memory[addr]) {
+                Instruction::Store { address } => Some(address),
+                _ => None,
+            })
+            .collect();
+        let constant = |addr: Address| {
+            (self.debug_info.word_kind(addr) == Some(WordKind::Data) && !stored_to.contains(&addr))
+                .then(|| self.memory[addr])
+        };
+
+        // The accumulator is reset to 0 along with the PC.
+        let accumulators =
+            self.dataflow(
+                Some(U12::from_u16(0)),
+                |acc, instruction| match instruction {
+                    Instruction::Clear => Some(U12::from_u16(0)),
+                    Instruction::Load { address } => constant(address),
+                    Instruction::Add { address } => acc
+                        .zip(constant(address))
+                        .map(|(acc, value)| wrapping(u16::from(acc) + u16::from(value))),
+                    Instruction::Decrease => acc.map(|acc| wrapping(u16::from(acc) + 0o7777)),
+                    _ => *acc,
+                },
+            );
+
+        for (addr, acc) in accumulators {
+            let acc = match (Instruction::from(self.memory[addr]), acc) {
+                (Instruction::BranchIfZero { .. }, Some(acc)) => u16::from(acc),
+                _ => continue,
+            };
+            let message = if acc == 0 {
+                "The accumulator is always 0 here, so this BZ always branches".to_string()
+            } else {
+                format!(
+                    "The accumulator is always {} here, so this BZ never branches",
+                    acc
+                )
+            };
+            self.warn_at_word(Lint::ConstantBranch, addr, message);
+        }
+    }
+
+    /// Computes a fact about the machine before each reachable instruction, starting from
+    /// `entry` and applying `transfer` along every edge of the control flow graph. Facts where
+    /// paths meet that disagree are merged with [`Fact::merge`].
+    fn dataflow<F: Fact>(
+        &self,
+        entry: F,
+        transfer: impl Fn(&F, Instruction) -> F,
+    ) -> BTreeMap<Address, F> {
+        let mut facts = BTreeMap::from([(Address::default(), entry)]);
+        let mut pending = vec![Address::default()];
+        while let Some(addr) = pending.pop() {
+            if self.debug_info.word_kind(addr) != Some(WordKind::Instruction) {
+                continue;
+            }
+            let after = transfer(&facts[&addr], Instruction::from(self.memory[addr]));
+            for &successor in self.cfg.successors(addr) {
+                let merged = match facts.get(&successor) {
+                    Some(fact) => fact.merge(&after),
+                    None => after.clone(),
+                };
+                if facts.get(&successor) != Some(&merged) {
+                    facts.insert(successor, merged);
+                    pending.push(successor);
+                }
+            }
+        }
+        facts.retain(|addr, _| self.debug_info.word_kind(*addr) == Some(WordKind::Instruction));
+        facts
+    }
+
+    /// Names the word at `addr` by its label, if it has one.
+    fn describe(&self, addr: Address) -> String {
+        match self
+            .debug_info
+            .symbols()
+            .iter()
+            .find(|symbol| symbol.address == addr)
+        {
+            Some(symbol) => format!("`{}` ({})", symbol.name, addr),
+            None => format!("the RES word at {}", addr),
+        }
+    }
+
+    /// Warns about the statement the word at `addr` was assembled from, if it is known.
+    fn warn_at_word(&mut self, lint: Lint, addr: Address, message: String) {
+        if let Some(line) = self.debug_info.source_line(addr) {
+            let span = self.statement_span(line);
+            self.warn(lint, span, message);
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, span: Range<usize>, message: String) {
+        let warning = Warning {
+            lint,
+            line: crate::line_number(self.source, &self.source[span.start..]),
+            span,
+            message,
+        };
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// The span of the mnemonic or directive on the zero-based `line`, or of the whole line if
+    /// it has none.
+    fn statement_span(&self, line: usize) -> Range<usize> {
+        let start: usize = self
+            .source
+            .split('\n')
+            .take(line)
+            .map(|line| line.len() + 1)
+            .sum();
+        let end = self.source[start..]
+            .find('\n')
+            .map_or(self.source.len(), |len| start + len);
+        self.tokens
+            .iter()
+            .find(|token| {
+                matches!(token.kind, TokenKind::Mnemonic | TokenKind::Directive)
+                    && (start..end).contains(&token.span.start)
+            })
+            .map_or(start..end, |token| token.span.clone())
+    }
+}
+
+/// Something known about the machine at a point of the program, for [`Linter::dataflow`].
+trait Fact: Clone + PartialEq {
+    /// Combines what is known on two paths that lead to the same instruction.
+    fn merge(&self, other: &Self) -> Self;
+}
+
+/// The words that may not have been written yet.
+impl Fact for BTreeSet<Address> {
+    fn merge(&self, other: &Self) -> Self {
+        self.union(other).copied().collect()
+    }
+}
+
+/// The value of the accumulator, if it is known.
+impl Fact for Option<U12> {
+    fn merge(&self, other: &Self) -> Self {
+        if self == other {
+            *self
+        } else {
+            None
+        }
+    }
+}
+
+fn addresses(memory: &Memory) -> impl Iterator<Item = Address> {
+    (0..memory.0.len() as u16).map(|addr| Address(U12::from_u16(addr)))
+}
+
+fn wrapping(value: u16) -> U12 {
+    U12::from_u16(value & 0o7777)
+}
+
+#[cfg(test)]
+#[test]
+fn lint_mistakes() {
+    let source = "        LD /uno\n\
+                  \x20       BZ /fin\n\
+                  \x20       LD /x\n\
+                  \x20       ST /y\n\
+                  fin     HALT\n\
+                  \x20       BR /fin\n\
+                  unused  DEC\n\
+                  uno     DATA 1\n\
+                  x       RES 1\n\
+                  y       RES 1\n";
+    let warnings: Vec<(Lint, usize)> = lint(source)
+        .unwrap()
+        .into_iter()
+        .map(|warning| (warning.lint, warning.line))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (Lint::ConstantBranch, 1),
+            (Lint::UninitializedRead, 2),
+            (Lint::UnreachableCode, 5),
+            (Lint::UnusedLabel, 6),
+        ]
+    );
+
+    let falls_into_data = "        CLR\n        BZ /end\nend     DATA 3\n";
+    let warnings = lint(falls_into_data).unwrap();
+    assert_eq!(warnings[0].lint, Lint::ExecutesData);
+    assert_eq!(&falls_into_data[warnings[0].span.clone()], "BZ");
+    assert_eq!(warnings[1].lint, Lint::ConstantBranch);
+    assert_eq!(warnings.len(), 2);
+
+    let allowed = format!(
+        "        ; @allow constant-branch, executes-data\n{}",
+        falls_into_data
+    );
+    assert_eq!(lint(&allowed).unwrap(), Vec::new());
+}
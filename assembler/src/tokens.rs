@@ -0,0 +1,180 @@
+//! A forgiving, line-by-line tokenizer for Simplez assembly. Unlike the parser it never fails,
+//! so that editors can highlight programs while they are being typed.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::{DIRECTIVES, MNEMONICS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A label being defined at the start of a line.
+    LabelDefinition,
+    /// A label used as a parameter, such as `/loop`. `defined` tells whether the program defines
+    /// a label with that name.
+    LabelReference {
+        defined: bool,
+    },
+    Mnemonic,
+    Directive,
+    /// An address parameter such as `/12`.
+    Address,
+    Number,
+    Comment,
+    /// Text that can't appear where it is, such as an unknown mnemonic.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range of the token in the tokenized source. Label references include their `/`.
+    pub span: Range<usize>,
+}
+
+impl Token {
+    /// Returns the name of the label this token defines or refers to, without the `/`.
+    pub fn label_name<'s>(&self, source: &'s str) -> Option<&'s str> {
+        match self.kind {
+            TokenKind::LabelDefinition => Some(&source[self.span.clone()]),
+            TokenKind::LabelReference { .. } => Some(&source[self.span.start + 1..self.span.end]),
+            _ => None,
+        }
+    }
+}
+
+/// Splits `source` into tokens, in order. Whitespace is not included.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut line_start = 0;
+    for line in source.split('\n') {
+        tokenize_line(line, line_start, &mut tokens);
+        line_start += line.len() + 1;
+    }
+
+    let defined: HashSet<&str> = tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::LabelDefinition)
+        .map(|token| &source[token.span.clone()])
+        .collect();
+    for token in &mut tokens {
+        if let TokenKind::LabelReference {
+            defined: is_defined,
+        } = &mut token.kind
+        {
+            *is_defined = defined.contains(&source[token.span.start + 1..token.span.end]);
+        }
+    }
+
+    tokens
+}
+
+/// Returns the token of `source` that contains the byte at `offset`, if any.
+pub fn token_at(source: &str, offset: usize) -> Option<Token> {
+    tokenize(source)
+        .into_iter()
+        .find(|token| token.span.contains(&offset))
+}
+
+fn tokenize_line(line: &str, line_start: usize, tokens: &mut Vec<Token>) {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let code_end = line.find(';').unwrap_or(line.len());
+
+    let mut words = words(&line[..code_end]);
+    if line.starts_with(|c: char| !c.is_whitespace()) {
+        if let Some((start, word)) = words.next() {
+            let kind = if is_label(word) {
+                TokenKind::LabelDefinition
+            } else {
+                TokenKind::Error
+            };
+            tokens.push(token(kind, line_start + start, word));
+        }
+    }
+    if let Some((start, word)) = words.next() {
+        let name = word.to_uppercase();
+        let kind = if MNEMONICS.contains(&name.as_str()) {
+            TokenKind::Mnemonic
+        } else if DIRECTIVES.contains(&name.as_str()) {
+            TokenKind::Directive
+        } else {
+            TokenKind::Error
+        };
+        tokens.push(token(kind, line_start + start, word));
+    }
+    for (start, word) in words {
+        tokens.push(token(parameter_kind(word), line_start + start, word));
+    }
+
+    if code_end < line.len() {
+        tokens.push(Token {
+            kind: TokenKind::Comment,
+            span: line_start + code_end..line_start + line.len(),
+        });
+    }
+}
+
+/// Returns the words in `text` separated by whitespace, along with their byte offsets.
+pub(crate) fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split([' ', '\t'])
+        .scan(0, |offset, word| {
+            let start = *offset;
+            *offset += word.len() + 1;
+            Some((start, word))
+        })
+        .filter(|(_, word)| !word.is_empty())
+}
+
+fn parameter_kind(word: &str) -> TokenKind {
+    match word.strip_prefix('/') {
+        Some(label) if is_label(label) => TokenKind::LabelReference { defined: false },
+        Some(address) if is_number(address.strip_prefix('-').unwrap_or(address)) => {
+            TokenKind::Address
+        }
+        None if is_number(word) => TokenKind::Number,
+        _ => TokenKind::Error,
+    }
+}
+
+/// Whether `word` is a valid label name, following the same rules as [`crate::parse_label`].
+pub fn is_label(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+pub(crate) fn is_number(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii_digit())
+}
+
+fn token(kind: TokenKind, start: usize, word: &str) -> Token {
+    Token {
+        kind,
+        span: start..start + word.len(),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tokenize_lines() {
+    let source = "loop    LD /x ; load\n        br /loop\n        FOO 12 /3 /-1 x";
+    let kinds: Vec<(TokenKind, &str)> = tokenize(source)
+        .into_iter()
+        .map(|token| (token.kind, &source[token.span]))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (TokenKind::LabelDefinition, "loop"),
+            (TokenKind::Mnemonic, "LD"),
+            (TokenKind::LabelReference { defined: false }, "/x"),
+            (TokenKind::Comment, "; load"),
+            (TokenKind::Mnemonic, "br"),
+            (TokenKind::LabelReference { defined: true }, "/loop"),
+            (TokenKind::Error, "FOO"),
+            (TokenKind::Number, "12"),
+            (TokenKind::Address, "/3"),
+            (TokenKind::Address, "/-1"),
+            (TokenKind::Error, "x"),
+        ]
+    );
+}
@@ -0,0 +1,26 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+use simplez_common::ControlFlowGraph;
+
+/// Print the control flow graph of a program in Graphviz's DOT language
+#[derive(Args)]
+pub struct CfgArgs {
+    /// The program to assemble
+    file: PathBuf,
+}
+
+pub fn run(args: CfgArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let source = fs::read_to_string(&args.file)
+        .map_err(|err| format!("Couldn't read {}: {}", args.file.display(), err))?;
+    let (memory, debug_info) = simplez_assembler::assemble_with_debug_info(&source)
+        .map_err(|err| format!("Couldn't assemble {}: {}", args.file.display(), err.kind))?;
+    print!(
+        "{}",
+        ControlFlowGraph::new(&memory, &debug_info).to_dot(&memory, &debug_info)
+    );
+    Ok(ExitCode::SUCCESS)
+}
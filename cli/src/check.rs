@@ -0,0 +1,34 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+use simplez_harness::spec::DEFAULT_MAX_STEPS;
+
+use crate::test::{self, ReportArgs};
+
+/// Run programs and check the `;@expect` assertions they make about themselves
+#[derive(Args)]
+pub struct CheckArgs {
+    /// The programs to check
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// How many instructions a program may execute before it is considered stuck
+    #[arg(long, default_value_t = DEFAULT_MAX_STEPS)]
+    max_steps: usize,
+    #[command(flatten)]
+    reports: ReportArgs,
+}
+
+pub fn run(args: CheckArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let mut suites = Vec::new();
+    for path in &args.files {
+        let source = test::read_program(path)?;
+        suites.push(simplez_harness::run_assertions(
+            &path.display().to_string(),
+            &source,
+            args.max_steps,
+        ));
+    }
+    test::finish(&suites, &args.reports)
+}
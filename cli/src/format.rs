@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::builder::RangedU64ValueParser;
+use clap::Args;
+use simplez_assembler::format::{self, FormatOptions, MnemonicCase};
+
+/// Align the columns of programs and normalize how they are written
+#[derive(Args)]
+pub struct FormatArgs {
+    /// The programs to format in place. Formats standard input to standard output if there are
+    /// none
+    files: Vec<PathBuf>,
+    /// Don't write anything, failing if a program isn't formatted
+    #[arg(long)]
+    check: bool,
+    /// Write mnemonics and directives in lower case
+    #[arg(long)]
+    lowercase: bool,
+    /// Start the mnemonic and comment columns at multiples of this many columns
+    #[arg(long, default_value_t = 8, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    tab_width: usize,
+}
+
+pub fn run(args: FormatArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let options = FormatOptions {
+        tab_width: args.tab_width,
+        case: if args.lowercase {
+            MnemonicCase::Lower
+        } else {
+            MnemonicCase::Upper
+        },
+    };
+
+    if args.files.is_empty() {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        let formatted = format::format(&source, options);
+        if args.check {
+            return Ok(check_result(formatted == source));
+        }
+        io::stdout().write_all(formatted.as_bytes())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut formatted_all = true;
+    for path in &args.files {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+        let formatted = format::format(&source, options);
+        if formatted == source {
+            continue;
+        }
+        if args.check {
+            println!("{} is not formatted", path.display());
+            formatted_all = false;
+        } else {
+            fs::write(path, formatted)
+                .map_err(|err| format!("Couldn't write {}: {}", path.display(), err))?;
+        }
+    }
+    Ok(check_result(formatted_all))
+}
+
+fn check_result(formatted: bool) -> ExitCode {
+    if formatted {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+use simplez_assembler::lint::{self, Lint, LintConfig};
+
+/// Warn about mistakes in programs that assemble
+#[derive(Args)]
+pub struct LintArgs {
+    /// The programs to check
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Don't check a lint, in addition to those the programs turn off with `;@allow` comments
+    #[arg(long, value_name = "LINT", value_parser = parse_lint)]
+    allow: Vec<Lint>,
+}
+
+pub fn run(args: LintArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let mut clean = true;
+    for path in &args.files {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+        let (memory, debug_info) = match simplez_assembler::assemble_with_debug_info(&source) {
+            Ok(program) => program,
+            Err(err) => {
+                let (line, column) = position(&source, err.span(&source).start);
+                println!(
+                    "{}:{}:{}: error: {}",
+                    path.display(),
+                    line,
+                    column,
+                    err.kind
+                );
+                clean = false;
+                continue;
+            }
+        };
+
+        let mut config = LintConfig::from_source(&source);
+        args.allow.iter().for_each(|lint| config.allow(*lint));
+        for warning in lint::lint_program(&source, &memory, &debug_info, &config) {
+            let (line, column) = position(&source, warning.span.start);
+            println!(
+                "{}:{}:{}: warning[{}]: {}",
+                path.display(),
+                line,
+                column,
+                warning.lint,
+                warning.message
+            );
+            clean = false;
+        }
+    }
+    Ok(if clean {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn parse_lint(code: &str) -> Result<Lint, String> {
+    Lint::from_code(code).ok_or_else(|| {
+        let codes: Vec<&str> = Lint::ALL.iter().map(|lint| lint.code()).collect();
+        format!("expected one of {}", codes.join(", "))
+    })
+}
+
+/// The one-based line and column of the byte at `offset`, as editors show them.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
@@ -0,0 +1,51 @@
+//! Command line tools for Simplez programs.
+
+mod cfg;
+mod check;
+mod format;
+mod lint;
+mod run;
+mod test;
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(
+    name = "simplez",
+    version,
+    about = "Tools for Simplez assembly programs"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    Cfg(cfg::CfgArgs),
+    Check(check::CheckArgs),
+    Format(format::FormatArgs),
+    Lint(lint::LintArgs),
+    Run(run::RunArgs),
+    Test(test::TestArgs),
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Cfg(args) => cfg::run(args),
+        Command::Check(args) => check::run(args),
+        Command::Format(args) => format::run(args),
+        Command::Lint(args) => lint::run(args),
+        Command::Run(args) => run::run(args),
+        Command::Test(args) => test::run(args),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
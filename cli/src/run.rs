@@ -0,0 +1,97 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+use simplez_common::cfg;
+use simplez_harness::spec::DEFAULT_MAX_STEPS;
+use simplez_interpreter::snapshot::Snapshot;
+use simplez_interpreter::{ExecutionContext, MachineState, StepOutcome};
+
+use crate::test;
+
+/// Assemble a program and run it until it halts
+#[derive(Args)]
+pub struct RunArgs {
+    /// The program to run
+    #[arg(required_unless_present = "snapshot", conflicts_with = "snapshot")]
+    file: Option<PathBuf>,
+    /// Resume the machine saved in a snapshot file instead of running a program from the start,
+    /// pausing at its breakpoints
+    #[arg(long, value_name = "PATH")]
+    snapshot: Option<PathBuf>,
+    /// Save the machine to a snapshot file once it stops
+    #[arg(long, value_name = "PATH")]
+    save_snapshot: Option<PathBuf>,
+    /// How many instructions the program may execute before it is stopped
+    #[arg(long, default_value_t = DEFAULT_MAX_STEPS)]
+    max_steps: usize,
+    /// Print how often each word was executed, read and written, most used first
+    #[arg(long)]
+    profile: bool,
+}
+
+pub fn run(args: RunArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let mut context = ExecutionContext::default();
+    match (&args.file, &args.snapshot) {
+        (_, Some(path)) => {
+            let text = test::read_program(path)?;
+            let snapshot = Snapshot::from_json(&text)
+                .map_err(|err| format!("Couldn't load {}: {}", path.display(), err))?;
+            context.restore(snapshot);
+        }
+        (Some(path), None) => {
+            let source = test::read_program(path)?;
+            let (memory, debug_info) = simplez_assembler::assemble_with_debug_info(&source)
+                .map_err(|err| format!("Couldn't assemble {}: {}", path.display(), err.kind))?;
+            context.set_memory(memory);
+            context.set_debug_info(debug_info);
+        }
+        (None, None) => unreachable!("clap requires a program or a snapshot"),
+    }
+    let outcome = context.run(args.max_steps);
+    if let Some(path) = &args.save_snapshot {
+        fs::write(path, context.snapshot().to_json())
+            .map_err(|err| format!("Couldn't write {}: {}", path.display(), err))?;
+    }
+
+    let state = match (outcome, context.state()) {
+        (StepOutcome::BreakpointHit(addr), _) => format!("Paused at breakpoint {}", addr),
+        (_, MachineState::Running) => "Stopped after the step limit".to_string(),
+        (_, state) => state.to_string(),
+    };
+    println!("State: {}", state);
+    println!("ACC: {}", u16::from(context.acc));
+    println!("PC: {}", context.pc);
+    println!("Instructions executed: {}", context.instructions_executed());
+
+    if args.profile {
+        let profile = context.profile();
+        let totals = profile.totals();
+        println!("\n{:>9} {:>9} {:>9}  Word", "Executed", "Read", "Written");
+        for (addr, counts) in profile.hottest() {
+            println!(
+                "{:>9} {:>9} {:>9}  {}",
+                counts.executions,
+                counts.reads,
+                counts.writes,
+                cfg::describe_word(context.memory(), context.debug_info(), addr)
+            );
+        }
+        println!(
+            "{:>9} {:>9} {:>9}  Total",
+            totals.executions, totals.reads, totals.writes
+        );
+    }
+
+    // Snapshots keep their breakpoints, and pausing at one is where the user asked to stop.
+    let stopped_as_asked = matches!(outcome, StepOutcome::BreakpointHit(_));
+    Ok(
+        if context.state() == MachineState::Halted || stopped_as_asked {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        },
+    )
+}
@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Args;
+use simplez_harness::{report, Spec, Status, SuiteResult};
+
+/// Run programs against the test cases of a TOML spec
+#[derive(Args)]
+pub struct TestArgs {
+    /// The spec with the test cases
+    spec: PathBuf,
+    /// The programs to test, such as every submission of an exercise. Defaults to the `program`
+    /// the spec names
+    programs: Vec<PathBuf>,
+    #[command(flatten)]
+    reports: ReportArgs,
+}
+
+/// Where to write the results of a test run, besides the terminal.
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Write the results to a file in the JUnit XML format
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+    /// Write the results to a file in JSON
+    #[arg(long, value_name = "PATH")]
+    json: Option<PathBuf>,
+}
+
+pub fn run(args: TestArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let text = fs::read_to_string(&args.spec)
+        .map_err(|err| format!("Couldn't read {}: {}", args.spec.display(), err))?;
+    let spec = Spec::parse(&text)
+        .map_err(|err| format!("Invalid spec {}: {}", args.spec.display(), err))?;
+
+    let programs = if !args.programs.is_empty() {
+        args.programs
+    } else if let Some(program) = &spec.program {
+        let dir = args.spec.parent().unwrap_or_else(|| "".as_ref());
+        vec![dir.join(program)]
+    } else {
+        return Err("The spec doesn't name a program, so one must be given".into());
+    };
+
+    let mut suites = Vec::new();
+    for path in &programs {
+        let source = read_program(path)?;
+        suites.push(simplez_harness::run_suite(
+            &spec,
+            &path.display().to_string(),
+            &source,
+        ));
+    }
+    finish(&suites, &args.reports)
+}
+
+pub fn read_program(path: &Path) -> Result<String, Box<dyn Error>> {
+    Ok(fs::read_to_string(path)
+        .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?)
+}
+
+/// Prints the results of every case, writes the reports that were asked for and returns whether
+/// every case passed.
+pub fn finish(suites: &[SuiteResult], reports: &ReportArgs) -> Result<ExitCode, Box<dyn Error>> {
+    for suite in suites {
+        for case in &suite.cases {
+            let verdict = match case.status {
+                Status::Passed => "PASS",
+                Status::Failed { .. } => "FAIL",
+                Status::Error { .. } => "ERROR",
+            };
+            println!("{} {}: {}", verdict, suite.program, case.name);
+            for line in report::diff(&case.status) {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    let passed: usize = suites.iter().map(|suite| suite.passed()).sum();
+    let total: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+    println!("\n{} of {} cases passed", passed, total);
+
+    if let Some(path) = &reports.junit {
+        fs::write(path, report::junit_xml(suites))
+            .map_err(|err| format!("Couldn't write {}: {}", path.display(), err))?;
+    }
+    if let Some(path) = &reports.json {
+        fs::write(path, report::json(suites))
+            .map_err(|err| format!("Couldn't write {}: {}", path.display(), err))?;
+    }
+
+    Ok(if passed == total {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
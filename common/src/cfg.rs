@@ -0,0 +1,264 @@
+//! The control flow of assembled programs: which instructions can execute after which, and the
+//! basic blocks they form.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use twelve_bit::u12::U12;
+
+use crate::{Address, DebugInfo, Instruction, Memory, WordKind};
+
+/// Where execution can go after each word of a program, assuming the program doesn't modify
+/// its own code. Execution starts at `/0`, where the PC is reset to, and stops at words that
+/// weren't assembled from an instruction, since what they would do isn't known.
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph {
+    /// Indexed by address, the addresses that can execute right after each reachable
+    /// instruction.
+    successors: Vec<Vec<Address>>,
+    /// Every word execution can reach from the entry point, instruction or not.
+    reachable: BTreeSet<Address>,
+    /// The reachable words split into basic blocks, in address order.
+    blocks: Vec<BasicBlock>,
+}
+
+/// A run of consecutive words that always execute one after the other: execution can only
+/// enter at `start` and only leave after `end`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: Address,
+    /// The last word of the block, which may branch.
+    pub end: Address,
+    /// The start of the blocks that can execute after this one.
+    pub successors: Vec<Address>,
+}
+
+impl BasicBlock {
+    pub fn contains(&self, addr: Address) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+
+    /// The addresses of the block's words, in order.
+    pub fn addresses(&self) -> impl Iterator<Item = Address> {
+        (u16::from(self.start.0)..=u16::from(self.end.0)).map(|addr| Address(U12::from_u16(addr)))
+    }
+}
+
+impl ControlFlowGraph {
+    pub fn new(memory: &Memory, debug_info: &DebugInfo) -> Self {
+        let mut graph = Self {
+            successors: vec![Vec::new(); memory.0.len()],
+            reachable: BTreeSet::new(),
+            blocks: Vec::new(),
+        };
+        let mut pending = vec![Address::default()];
+        while let Some(addr) = pending.pop() {
+            if !graph.reachable.insert(addr)
+                || debug_info.word_kind(addr) != Some(WordKind::Instruction)
+            {
+                continue;
+            }
+            let successors = successors(memory, addr);
+            pending.extend(successors.iter().copied());
+            graph.successors[usize::from(addr.0)] = successors;
+        }
+        graph.blocks = graph.basic_blocks(debug_info);
+        graph
+    }
+
+    /// Splits the reachable words into blocks. A block starts at the entry point, at words that
+    /// are branched to or that can be reached from several places, and at words that weren't
+    /// assembled from an instruction, which are blocks of their own.
+    fn basic_blocks(&self, debug_info: &DebugInfo) -> Vec<BasicBlock> {
+        let is_instruction = |addr| debug_info.word_kind(addr) == Some(WordKind::Instruction);
+        let is_leader = |addr: Address| {
+            let mut predecessors = self.predecessors(addr);
+            match (predecessors.next(), predecessors.next()) {
+                (Some(pred), None) => {
+                    self.successors(pred) != [addr]
+                        || u16::from(pred.0) + 1 != u16::from(addr.0)
+                        || !is_instruction(pred)
+                        || !is_instruction(addr)
+                }
+                _ => true,
+            }
+        };
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        for addr in self.reachable() {
+            match blocks.last_mut() {
+                Some(block) if !is_leader(addr) => block.end = addr,
+                _ => blocks.push(BasicBlock {
+                    start: addr,
+                    end: addr,
+                    successors: Vec::new(),
+                }),
+            }
+        }
+        for block in &mut blocks {
+            block.successors = self.successors(block.end).to_vec();
+        }
+        blocks
+    }
+
+    /// Whether execution can reach the word at `addr`.
+    pub fn is_reachable(&self, addr: Address) -> bool {
+        self.reachable.contains(&addr)
+    }
+
+    /// Every word execution can reach, in address order.
+    pub fn reachable(&self) -> impl Iterator<Item = Address> + '_ {
+        self.reachable.iter().copied()
+    }
+
+    /// The addresses that can execute right after the instruction at `addr`, if it is reachable.
+    pub fn successors(&self, addr: Address) -> &[Address] {
+        self.successors
+            .get(usize::from(addr.0))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The reachable words that execution can go to `addr` from.
+    pub fn predecessors(&self, addr: Address) -> impl Iterator<Item = Address> + '_ {
+        self.reachable()
+            .filter(move |pred| self.successors(*pred).contains(&addr))
+    }
+
+    /// The basic blocks of the reachable words, in address order.
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// The block the word at `addr` belongs to, if it is reachable.
+    pub fn block_containing(&self, addr: Address) -> Option<&BasicBlock> {
+        self.blocks.iter().find(|block| block.contains(addr))
+    }
+
+    /// Describes the graph in Graphviz's DOT language, with a node for every block listing its
+    /// words. The edges `BZ` takes when the accumulator is zero are labelled `zero`.
+    pub fn to_dot(&self, memory: &Memory, debug_info: &DebugInfo) -> String {
+        let mut dot =
+            String::from("digraph program {\n    node [shape=box, fontname=monospace];\n");
+        for block in &self.blocks {
+            let mut label = String::new();
+            for addr in block.addresses() {
+                let _ = write!(label, "{}\\l", describe_word(memory, debug_info, addr));
+            }
+            let _ = writeln!(
+                dot,
+                "    b{} [label=\"{}\"];",
+                u16::from(block.start.0),
+                label
+            );
+        }
+        for block in &self.blocks {
+            let branch = match Instruction::from(memory[block.end]) {
+                Instruction::BranchIfZero { address } if block.successors.len() == 2 => {
+                    Some(address)
+                }
+                _ => None,
+            };
+            for successor in &block.successors {
+                let _ = write!(
+                    dot,
+                    "    b{} -> b{}",
+                    u16::from(block.start.0),
+                    u16::from(successor.0)
+                );
+                if branch == Some(*successor) {
+                    dot.push_str(" [label=zero]");
+                }
+                dot.push_str(";\n");
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Describes the word at `addr` the way it was assembled, along with its label, such as
+/// `/4 loop: LD /12` or `/12 DATA 3`.
+pub fn describe_word(memory: &Memory, debug_info: &DebugInfo, addr: Address) -> String {
+    let mut description = addr.to_string();
+    if let Some(symbol) = debug_info
+        .symbols()
+        .iter()
+        .find(|symbol| symbol.address == addr)
+    {
+        let _ = write!(description, " {}:", symbol.name);
+    }
+    let word = memory[addr];
+    let _ = match debug_info.word_kind(addr) {
+        Some(WordKind::Instruction) => write!(description, " {}", Instruction::from(word)),
+        Some(WordKind::Data) => write!(description, " DATA {}", u16::from(word)),
+        Some(WordKind::Reserved) => write!(description, " RES ({})", u16::from(word)),
+        None => write!(description, " ? ({})", u16::from(word)),
+    };
+    description
+}
+
+/// The addresses that can execute after the word at `addr`, decoded as an instruction.
+fn successors(memory: &Memory, addr: Address) -> Vec<Address> {
+    let next = Address(U12::from_u16(
+        (u16::from(addr.0) + 1) % memory.0.len() as u16,
+    ));
+    match Instruction::from(memory[addr]) {
+        Instruction::Branch { address } => vec![address],
+        Instruction::BranchIfZero { address } if address != next => vec![next, address],
+        Instruction::Halt => Vec::new(),
+        _ => vec![next],
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn basic_blocks() {
+    let addr = |addr: u16| Address(U12::from_u16(addr));
+    let program = [
+        Instruction::Load { address: addr(5) },
+        Instruction::BranchIfZero { address: addr(4) },
+        Instruction::Decrease,
+        Instruction::Branch { address: addr(1) },
+        Instruction::Halt,
+    ];
+    let mut memory = Memory::default();
+    let mut debug_info = DebugInfo::default();
+    for (idx, instruction) in program.into_iter().enumerate() {
+        memory[addr(idx as u16)] = U12::from(instruction);
+        debug_info.set_word_kind(addr(idx as u16), WordKind::Instruction);
+    }
+    debug_info.set_word_kind(addr(5), WordKind::Data);
+
+    let cfg = ControlFlowGraph::new(&memory, &debug_info);
+    let blocks: Vec<(u16, u16, Vec<u16>)> = cfg
+        .blocks()
+        .iter()
+        .map(|block| {
+            (
+                u16::from(block.start.0),
+                u16::from(block.end.0),
+                block
+                    .successors
+                    .iter()
+                    .map(|addr| u16::from(addr.0))
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        blocks,
+        [
+            (0, 0, vec![1]),
+            (1, 1, vec![2, 4]),
+            (2, 3, vec![1]),
+            (4, 4, vec![]),
+        ]
+    );
+    assert_eq!(cfg.block_containing(addr(3)).unwrap().start, addr(2));
+    assert!(!cfg.is_reachable(addr(5)));
+
+    let dot = cfg.to_dot(&memory, &debug_info);
+    assert!(dot.contains("b2 [label=\"/2 DEC\\l/3 BR /1\\l\"];"));
+    assert!(dot.contains("b1 -> b4 [label=zero];"));
+    assert!(dot.contains("b1 -> b2;"));
+}
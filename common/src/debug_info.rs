@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+
+use twelve_bit::u12::U12;
+
+use crate::Address;
+
+/// What a word of memory was assembled from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WordKind {
+    /// An instruction mnemonic such as `LD` or `HALT`.
+    Instruction,
+    /// A `DATA` directive.
+    Data,
+    /// Space reserved by a `RES` directive.
+    Reserved,
+}
+
+/// A label defined by the program.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    /// The address the label stands for.
+    pub address: Address,
+    /// The zero-based line of the source the label is defined on.
+    pub line: usize,
+}
+
+/// What an [`Assertion`] checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AssertionTarget {
+    Acc,
+    Word(Address),
+}
+
+/// An expectation the program states about itself with a comment such as `;@expect suma = 20`,
+/// to be checked once it halts.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Assertion {
+    /// The target as written in the source, such as `suma`, `/12` or `ACC`.
+    pub name: String,
+    pub target: AssertionTarget,
+    pub expected: U12,
+    /// The zero-based line of the source the assertion is written on.
+    pub line: usize,
+}
+
+/// Information recorded by the assembler about the program it produced, so that it can be
+/// reasoned about at run time.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DebugInfo {
+    /// Indexed by address. Addresses past the end have not been assembled to.
+    word_kinds: Vec<Option<WordKind>>,
+    /// Indexed by address, the zero-based line of the source each word was assembled from.
+    #[serde(default)]
+    source_lines: Vec<Option<usize>>,
+    /// Every label of the program, in the order they are defined.
+    #[serde(default)]
+    symbols: Vec<Symbol>,
+    /// Every `;@expect` assertion of the program, in source order.
+    #[serde(default)]
+    assertions: Vec<Assertion>,
+}
+
+impl DebugInfo {
+    /// Returns what the word at `addr` was assembled from, or `None` if the assembler never
+    /// touched it.
+    pub fn word_kind(&self, addr: Address) -> Option<WordKind> {
+        self.word_kinds.get(usize::from(addr.0)).copied().flatten()
+    }
+
+    pub fn set_word_kind(&mut self, addr: Address, kind: WordKind) {
+        let idx = usize::from(addr.0);
+        if idx >= self.word_kinds.len() {
+            self.word_kinds.resize(idx + 1, None);
+        }
+        self.word_kinds[idx] = Some(kind);
+    }
+
+    /// Returns the zero-based line of the source the word at `addr` was assembled from.
+    pub fn source_line(&self, addr: Address) -> Option<usize> {
+        self.source_lines
+            .get(usize::from(addr.0))
+            .copied()
+            .flatten()
+    }
+
+    pub fn set_source_line(&mut self, addr: Address, line: usize) {
+        let idx = usize::from(addr.0);
+        if idx >= self.source_lines.len() {
+            self.source_lines.resize(idx + 1, None);
+        }
+        self.source_lines[idx] = Some(line);
+    }
+
+    /// Returns the address of the instruction assembled from the zero-based `line` of the
+    /// source, if there is one.
+    pub fn instruction_at_line(&self, line: usize) -> Option<Address> {
+        (0..self.source_lines.len())
+            .map(|idx| Address(U12::from_u16(idx as u16)))
+            .find(|addr| {
+                self.source_line(*addr) == Some(line)
+                    && self.word_kind(*addr) == Some(WordKind::Instruction)
+            })
+    }
+
+    /// Returns the position of the statement the word at `addr` was assembled from, counting
+    /// only statements that were assembled to at least one word, in source order. Unlike
+    /// [`Self::source_line`], it doesn't change when comments or blank lines are added.
+    pub fn statement_index(&self, addr: Address) -> Option<usize> {
+        let line = self.source_line(addr)?;
+        let earlier_lines: BTreeSet<usize> = self
+            .source_lines
+            .iter()
+            .flatten()
+            .filter(|other| **other < line)
+            .copied()
+            .collect();
+        Some(earlier_lines.len())
+    }
+
+    /// Whether the word at `addr` is of the same kind and was assembled from the same statement
+    /// in both programs, telling if code was moved around it when the source was edited.
+    pub fn same_statement_at(&self, other: &DebugInfo, addr: Address) -> bool {
+        self.word_kind(addr) == other.word_kind(addr)
+            && self.statement_index(addr) == other.statement_index(addr)
+    }
+
+    /// Returns every label of the program, in the order they are defined.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns the label called `name`, if the program defines it.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    pub fn add_symbol(&mut self, symbol: Symbol) {
+        self.symbols.push(symbol);
+    }
+
+    /// Returns every `;@expect` assertion of the program, in source order.
+    pub fn assertions(&self) -> &[Assertion] {
+        &self.assertions
+    }
+
+    pub fn add_assertion(&mut self, assertion: Assertion) {
+        self.assertions.push(assertion);
+    }
+}
@@ -0,0 +1,91 @@
+//! Comparisons between two states of memory, to see what running part of a program changed.
+
+use twelve_bit::u12::U12;
+
+use crate::{Address, DebugInfo, Instruction, Memory, WordKind};
+
+/// A word whose value differs between two states of memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordChange {
+    pub address: Address,
+    /// The label defined at the word, if any.
+    pub label: Option<String>,
+    /// What the word was assembled from, if it was.
+    pub kind: Option<WordKind>,
+    pub old: U12,
+    pub new: U12,
+}
+
+impl WordChange {
+    pub fn old_instruction(&self) -> Instruction {
+        Instruction::from(self.old)
+    }
+
+    pub fn new_instruction(&self) -> Instruction {
+        Instruction::from(self.new)
+    }
+}
+
+/// The words that differ between `old` and `new`, in address order. Labels and word kinds are
+/// taken from `debug_info`, which should describe `new`.
+pub fn compare(old: &Memory, new: &Memory, debug_info: &DebugInfo) -> Vec<WordChange> {
+    (0..old.0.len() as u16)
+        .map(|addr| Address(U12::from_u16(addr)))
+        .filter(|&addr| old[addr] != new[addr])
+        .map(|addr| WordChange {
+            address: addr,
+            label: debug_info
+                .symbols()
+                .iter()
+                .find(|symbol| symbol.address == addr)
+                .map(|symbol| symbol.name.clone()),
+            kind: debug_info.word_kind(addr),
+            old: old[addr],
+            new: new[addr],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn changed_words() {
+    use crate::Symbol;
+
+    let addr = |addr: u16| Address(U12::from_u16(addr));
+    let mut old = Memory::default();
+    old[addr(0)] = U12::from(Instruction::Load { address: addr(10) });
+    old[addr(10)] = U12::from_u16(3);
+    let mut new = old.clone();
+    new[addr(10)] = U12::from_u16(7);
+    new[addr(511)] = U12::from(Instruction::Halt);
+    let mut debug_info = DebugInfo::default();
+    debug_info.set_word_kind(addr(10), WordKind::Data);
+    debug_info.add_symbol(Symbol {
+        name: "x".to_string(),
+        address: addr(10),
+        line: 2,
+    });
+
+    let changes = compare(&old, &new, &debug_info);
+    assert_eq!(
+        changes,
+        [
+            WordChange {
+                address: addr(10),
+                label: Some("x".to_string()),
+                kind: Some(WordKind::Data),
+                old: U12::from_u16(3),
+                new: U12::from_u16(7),
+            },
+            WordChange {
+                address: addr(511),
+                label: None,
+                kind: None,
+                old: U12::from_u16(0),
+                new: U12::from(Instruction::Halt),
+            },
+        ]
+    );
+    assert_eq!(changes[1].new_instruction().to_string(), "HALT");
+    assert!(compare(&new, &new, &debug_info).is_empty());
+}
@@ -1,11 +1,21 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut};
 
 use twelve_bit::u12;
 use twelve_bit::u12::*;
 
+pub mod cfg;
+pub mod debug_info;
+pub mod diff;
+pub mod share;
 pub mod util;
 
+pub use cfg::ControlFlowGraph;
+pub use debug_info::{Assertion, AssertionTarget, DebugInfo, Symbol, WordKind};
+pub use diff::WordChange;
+
 #[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Instruction<Addr = Address> {
     Store { address: Addr },
@@ -36,6 +46,51 @@ impl From<U12> for Instruction<Address> {
     }
 }
 
+impl From<Instruction<Address>> for U12 {
+    fn from(ins: Instruction<Address>) -> Self {
+        let (opcode, address) = match ins {
+            Instruction::Store { address } => (0, address),
+            Instruction::Load { address } => (1, address),
+            Instruction::Add { address } => (2, address),
+            Instruction::Branch { address } => (3, address),
+            Instruction::BranchIfZero { address } => (4, address),
+            Instruction::Clear => (5, Address::default()),
+            Instruction::Decrease => (6, Address::default()),
+            Instruction::Halt => (7, Address::default()),
+        };
+        U12::from_u16(opcode << 9) | address.0 & u12!(0o777)
+    }
+}
+
+impl<A> Instruction<A> {
+    /// Converts the address of the instruction (if it has one) with `f`, failing if `f` does.
+    pub fn try_map_address<B, E>(
+        self,
+        f: impl FnOnce(A) -> Result<B, E>,
+    ) -> Result<Instruction<B>, E> {
+        Ok(match self {
+            Instruction::Store { address } => Instruction::Store {
+                address: f(address)?,
+            },
+            Instruction::Load { address } => Instruction::Load {
+                address: f(address)?,
+            },
+            Instruction::Add { address } => Instruction::Add {
+                address: f(address)?,
+            },
+            Instruction::Branch { address } => Instruction::Branch {
+                address: f(address)?,
+            },
+            Instruction::BranchIfZero { address } => Instruction::BranchIfZero {
+                address: f(address)?,
+            },
+            Instruction::Clear => Instruction::Clear,
+            Instruction::Decrease => Instruction::Decrease,
+            Instruction::Halt => Instruction::Halt,
+        })
+    }
+}
+
 impl Display for Instruction<Address> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -54,6 +109,24 @@ impl Display for Instruction<Address> {
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Address(pub U12);
 
+impl PartialOrd for Address {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Address {
+    fn cmp(&self, other: &Self) -> Ordering {
+        u16::from(self.0).cmp(&u16::from(other.0))
+    }
+}
+
+impl Hash for Address {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        u16::from(self.0).hash(state)
+    }
+}
+
 impl Display for Address {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("/{}", u16::from(self.0)))
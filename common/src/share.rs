@@ -0,0 +1,93 @@
+//! Programs encoded into links, so that they can be shared by sending a URL. The program is
+//! compressed with DEFLATE and encoded in unpadded base64url, so that it can be put in the
+//! fragment of a URL as `#program=...` without escaping.
+
+use std::fmt::Display;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+/// What comes before the encoded program in the fragment of a share link.
+pub const FRAGMENT_KEY: &str = "program=";
+
+/// The longest program, in bytes, a share link may carry. Links are short, but compressed data
+/// can expand to a lot more, so decoding stops here instead of filling the memory.
+pub const MAX_PROGRAM_LEN: usize = 1 << 20;
+
+/// Why a shared program couldn't be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShareError {
+    /// The text isn't base64url.
+    InvalidEncoding,
+    /// The data isn't DEFLATE compressed, or decompresses to more than [`MAX_PROGRAM_LEN`] bytes.
+    InvalidCompression,
+    /// The program isn't UTF-8 text.
+    InvalidText,
+}
+
+impl Display for ShareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShareError::InvalidEncoding => f.write_str("The link is not valid base64url"),
+            ShareError::InvalidCompression => f.write_str("The link's program is corrupted"),
+            ShareError::InvalidText => f.write_str("The link's program is not text"),
+        }
+    }
+}
+
+impl std::error::Error for ShareError {}
+
+/// Compresses and encodes `program` for a share link.
+pub fn encode(program: &str) -> String {
+    let compressed = miniz_oxide::deflate::compress_to_vec(program.as_bytes(), 9);
+    URL_SAFE_NO_PAD.encode(compressed)
+}
+
+/// Decodes a program encoded by [`encode`].
+pub fn decode(encoded: &str) -> Result<String, ShareError> {
+    let compressed = URL_SAFE_NO_PAD
+        .decode(encoded.trim())
+        .map_err(|_| ShareError::InvalidEncoding)?;
+    let bytes = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_PROGRAM_LEN)
+        .map_err(|_| ShareError::InvalidCompression)?;
+    String::from_utf8(bytes).map_err(|_| ShareError::InvalidText)
+}
+
+/// Makes a link to `page` that opens `program`. Any fragment `page` already has is replaced.
+pub fn share_link(page: &str, program: &str) -> String {
+    let page = page.split('#').next().unwrap_or(page);
+    format!("{}#{}{}", page, FRAGMENT_KEY, encode(program))
+}
+
+/// Returns the program a URL fragment such as `#program=...` carries, or `None` if it doesn't
+/// carry any.
+pub fn program_from_fragment(fragment: &str) -> Option<Result<String, ShareError>> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    fragment.strip_prefix(FRAGMENT_KEY).map(decode)
+}
+
+#[cfg(test)]
+#[test]
+fn round_trip() {
+    let program = "        LD /x   ; ¿Cargar?\n        HALT\nx       DATA 7\n";
+    let link = share_link("https://example.com/simplez/#old", program);
+    assert!(link.starts_with("https://example.com/simplez/#program="));
+    let fragment = &link[link.find('#').unwrap()..];
+    assert!(fragment[1..]
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || b"-_=".contains(&byte)));
+    assert_eq!(
+        program_from_fragment(fragment),
+        Some(Ok(program.to_string()))
+    );
+
+    assert_eq!(program_from_fragment(""), None);
+    assert_eq!(program_from_fragment("#other"), None);
+    assert_eq!(decode("not base64!"), Err(ShareError::InvalidEncoding));
+    assert_eq!(decode("AAAA"), Err(ShareError::InvalidCompression));
+
+    let huge = " ".repeat(MAX_PROGRAM_LEN + 1);
+    assert_eq!(decode(&encode(&huge)), Err(ShareError::InvalidCompression));
+    let largest = " ".repeat(MAX_PROGRAM_LEN);
+    assert_eq!(decode(&encode(&largest)), Ok(largest));
+}
@@ -0,0 +1,75 @@
+//! A debug adapter for Simplez programs, talking the Debug Adapter Protocol over stdio.
+
+mod protocol;
+mod session;
+
+use std::io;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use protocol::Request;
+use session::Session;
+
+fn main() -> io::Result<()> {
+    // Requests are read on their own thread so that the machine can keep running until the
+    // client pauses it.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut reader = stdin.lock();
+        loop {
+            let message = match protocol::read_message(&mut reader) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                // The whole message was read, so the next one can still be.
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    eprintln!("Ignoring malformed message: {}", err);
+                    continue;
+                }
+                Err(err) => {
+                    eprintln!("Couldn't read from the client: {}", err);
+                    break;
+                }
+            };
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::default();
+    let mut seq = 0;
+    let stdout = io::stdout();
+    while !session.is_finished() {
+        let message = if session.is_running() {
+            match receiver.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            }
+        };
+
+        // Responses to requests made by the adapter are not expected, so only requests are
+        // handled.
+        if let Some(message) = message.filter(|message| message["type"] == "request") {
+            match serde_json::from_value::<Request>(message) {
+                Ok(request) => session.handle(request),
+                Err(err) => eprintln!("Ignoring malformed request: {}", err),
+            }
+        }
+        session.run();
+
+        let mut writer = stdout.lock();
+        for message in session.take_messages() {
+            seq += 1;
+            protocol::write_message(&mut writer, seq, message)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! The messages of the Debug Adapter Protocol and how they are framed on a stream.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+/// A request sent by the client.
+#[derive(Debug, serde::Deserialize)]
+pub struct Request {
+    pub seq: u64,
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+impl Request {
+    /// Deserializes the arguments of the request, failing with a message for the client.
+    pub fn arguments<T: serde::de::DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_value(self.arguments.clone())
+            .map_err(|err| format!("Invalid arguments for {}: {}", self.command, err))
+    }
+
+    pub fn response(&self, body: Value) -> Value {
+        json!({
+            "type": "response",
+            "request_seq": self.seq,
+            "success": true,
+            "command": self.command,
+            "body": body,
+        })
+    }
+
+    pub fn error_response(&self, message: &str) -> Value {
+        json!({
+            "type": "response",
+            "request_seq": self.seq,
+            "success": false,
+            "command": self.command,
+            "message": message,
+        })
+    }
+}
+
+pub fn event(name: &str, body: Value) -> Value {
+    json!({
+        "type": "event",
+        "event": name,
+        "body": body,
+    })
+}
+
+/// Reads the next message from `reader`, returning `None` at the end of the stream.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length"))?;
+
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes `message` to `writer` as the message number `seq`.
+pub fn write_message(writer: &mut impl Write, seq: u64, mut message: Value) -> io::Result<()> {
+    message["seq"] = seq.into();
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
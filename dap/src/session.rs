@@ -0,0 +1,607 @@
+//! A debugging session of a single Simplez program, answering the client's requests.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use simplez_common::{Address, Instruction};
+use simplez_interpreter::{ExecutionContext, MachineState, StepOutcome};
+use twelve_bit::u12::U12;
+
+use crate::protocol::{event, Request};
+
+/// How many instructions are executed between checks for new requests while running.
+const STEPS_PER_BATCH: usize = 10_000;
+/// Simplez has a single thread of execution, which is reported with this ID.
+const THREAD_ID: u64 = 1;
+const REGISTERS_REFERENCE: u64 = 1;
+const LABELS_REFERENCE: u64 = 2;
+/// Memory is addressed in bytes, each word being stored in two of them, low byte first.
+const BYTES_PER_WORD: usize = 2;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeArguments {
+    #[serde(default = "default_true")]
+    lines_start_at1: bool,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchArguments {
+    /// Path of the `.sz` source to debug.
+    program: String,
+    #[serde(default)]
+    stop_on_entry: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct SetBreakpointsArguments {
+    #[serde(default)]
+    breakpoints: Vec<SourceBreakpoint>,
+}
+
+#[derive(serde::Deserialize)]
+struct SourceBreakpoint {
+    line: usize,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariablesArguments {
+    variables_reference: u64,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetVariableArguments {
+    variables_reference: u64,
+    name: String,
+    value: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadMemoryArguments {
+    memory_reference: String,
+    #[serde(default)]
+    offset: i64,
+    count: usize,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteMemoryArguments {
+    memory_reference: String,
+    #[serde(default)]
+    offset: i64,
+    data: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The program being debugged.
+struct Program {
+    path: String,
+    stop_on_entry: bool,
+}
+
+pub struct Session {
+    program: Option<Program>,
+    context: ExecutionContext,
+    /// Whether the client numbers lines from 1 instead of 0.
+    lines_start_at1: bool,
+    running: bool,
+    finished: bool,
+    /// Responses and events to send to the client, in order.
+    outgoing: Vec<Value>,
+    /// Events caused by the request being handled, sent after its response.
+    events: Vec<Value>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            program: None,
+            context: Default::default(),
+            lines_start_at1: true,
+            running: false,
+            finished: false,
+            outgoing: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Session {
+    /// Whether the machine is running, so that [`Self::run`] should be called even if there
+    /// are no requests.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Whether the client has ended the session.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the messages to send to the client since this was last called.
+    pub fn take_messages(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    pub fn handle(&mut self, request: Request) {
+        let result = match request.command.as_str() {
+            "initialize" => self.initialize(&request),
+            "launch" => self.launch(&request),
+            "setBreakpoints" => self.set_breakpoints(&request),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "Simplez" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                    { "name": "Labels", "variablesReference": LABELS_REFERENCE, "expensive": false },
+                ]
+            })),
+            "variables" => self.variables(&request),
+            "setVariable" => self.set_variable(&request),
+            "continue" => self.resume(),
+            "next" | "stepIn" | "stepOut" => self.step(),
+            "pause" => self.pause(),
+            "readMemory" => self.read_memory(&request),
+            "writeMemory" => self.write_memory(&request),
+            "disconnect" | "terminate" => {
+                self.finished = true;
+                self.running = false;
+                Ok(json!({}))
+            }
+            _ => Err(format!("Unsupported request {}", request.command)),
+        };
+        self.outgoing.push(match result {
+            Ok(body) => request.response(body),
+            Err(message) => request.error_response(&message),
+        });
+        self.outgoing.append(&mut self.events);
+    }
+
+    /// Executes a batch of instructions if the machine is running, stopping at breakpoints.
+    pub fn run(&mut self) {
+        if !self.running {
+            return;
+        }
+        let outcome = self.context.run(STEPS_PER_BATCH);
+        if outcome != StepOutcome::StepLimitReached {
+            self.running = false;
+            self.stopped(outcome, "step");
+            self.outgoing.append(&mut self.events);
+        }
+    }
+
+    fn initialize(&mut self, request: &Request) -> Result<Value, String> {
+        let arguments: InitializeArguments = request.arguments()?;
+        self.lines_start_at1 = arguments.lines_start_at1;
+        Ok(json!({
+            "supportsConfigurationDoneRequest": true,
+            "supportsSetVariable": true,
+            "supportsReadMemoryRequest": true,
+            "supportsWriteMemoryRequest": true,
+            "supportsTerminateRequest": true,
+        }))
+    }
+
+    fn launch(&mut self, request: &Request) -> Result<Value, String> {
+        let arguments: LaunchArguments = request.arguments()?;
+        let source = std::fs::read_to_string(&arguments.program)
+            .map_err(|err| format!("Could not read {}: {}", arguments.program, err))?;
+        self.load(arguments.program, &source, arguments.stop_on_entry)?;
+        // Breakpoints can only be mapped to addresses once the program is assembled.
+        self.events.push(event("initialized", json!({})));
+        Ok(json!({}))
+    }
+
+    /// Assembles `source`, read from `path`, and resets the machine to run it.
+    fn load(&mut self, path: String, source: &str, stop_on_entry: bool) -> Result<(), String> {
+        let (memory, debug_info) =
+            simplez_assembler::assemble_with_debug_info(source).map_err(|err| {
+                let line = simplez_assembler::line_number(source, err.input) + 1;
+                format!("{}:{}: {}", path, line, err.kind)
+            })?;
+        self.context = ExecutionContext::default();
+        self.context.set_memory(memory);
+        self.context.set_debug_info(debug_info);
+        self.program = Some(Program {
+            path,
+            stop_on_entry,
+        });
+        Ok(())
+    }
+
+    fn set_breakpoints(&mut self, request: &Request) -> Result<Value, String> {
+        let arguments: SetBreakpointsArguments = request.arguments()?;
+        self.context.breakpoints.clear();
+        let breakpoints: Vec<Value> = arguments
+            .breakpoints
+            .iter()
+            .map(|breakpoint| {
+                let addr = self
+                    .source_line(breakpoint.line)
+                    .and_then(|line| self.context.debug_info().instruction_at_line(line));
+                match addr {
+                    Some(addr) => {
+                        self.context.breakpoints.insert(addr);
+                        json!({ "verified": true, "line": breakpoint.line })
+                    }
+                    None => json!({
+                        "verified": false,
+                        "line": breakpoint.line,
+                        "message": "There is no instruction on this line",
+                    }),
+                }
+            })
+            .collect();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn configuration_done(&mut self) -> Result<Value, String> {
+        match &self.program {
+            Some(program) if program.stop_on_entry => {
+                self.events.push(stopped_event("entry", None));
+            }
+            Some(_) => self.running = true,
+            None => return Err("No program has been launched".to_string()),
+        }
+        Ok(json!({}))
+    }
+
+    fn stack_trace(&self) -> Result<Value, String> {
+        let program = self
+            .program
+            .as_ref()
+            .ok_or("No program has been launched")?;
+        let pc = self.context.pc;
+        let line = match self.context.debug_info().source_line(pc) {
+            Some(line) => self.client_line(line),
+            None => 0,
+        };
+        Ok(json!({
+            "stackFrames": [{
+                "id": 0,
+                "name": Instruction::from(self.context.memory()[pc]).to_string(),
+                "source": { "path": program.path },
+                "line": line,
+                "column": self.client_line(0),
+                "instructionPointerReference": memory_reference(pc),
+            }],
+            "totalFrames": 1,
+        }))
+    }
+
+    fn variables(&self, request: &Request) -> Result<Value, String> {
+        let arguments: VariablesArguments = request.arguments()?;
+        let context = &self.context;
+        let variables: Vec<Value> = match arguments.variables_reference {
+            REGISTERS_REFERENCE => vec![
+                variable("ACC", u16::from(context.acc).to_string()),
+                variable("Z", context.zero().to_string()),
+                variable("PC", context.pc.to_string()),
+                variable("IR", Instruction::from(context.ir).to_string()),
+            ],
+            LABELS_REFERENCE => context
+                .debug_info()
+                .symbols()
+                .iter()
+                .filter_map(|symbol| {
+                    let word = context.memory().0.get(usize::from(symbol.address.0))?;
+                    let mut variable = variable(&symbol.name, u16::from(*word).to_string());
+                    variable["memoryReference"] = memory_reference(symbol.address).into();
+                    Some(variable)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Sets a register, or the word a label points to.
+    fn set_variable(&mut self, request: &Request) -> Result<Value, String> {
+        let arguments: SetVariableArguments = request.arguments()?;
+        let value = parse_word(&arguments.value)?;
+        match (arguments.variables_reference, arguments.name.as_str()) {
+            (REGISTERS_REFERENCE, "ACC") => self.context.acc = value,
+            (REGISTERS_REFERENCE, "PC") => {
+                if usize::from(u16::from(value)) >= self.context.memory().0.len() {
+                    return Err(format!("{} is past the end of memory", u16::from(value)));
+                }
+                self.context.pc = Address(value)
+            }
+            (REGISTERS_REFERENCE, "IR") => self.context.ir = value,
+            (LABELS_REFERENCE, name) => {
+                let addr = match self.context.debug_info().symbol(name) {
+                    Some(symbol) => symbol.address,
+                    None => return Err(format!("Undefined label `{}`", name)),
+                };
+                self.context.set_addr(addr, value);
+            }
+            (_, name) => return Err(format!("{} can't be changed", name)),
+        }
+        Ok(json!({ "value": u16::from(value).to_string() }))
+    }
+
+    fn resume(&mut self) -> Result<Value, String> {
+        if self.context.state() == MachineState::Halted {
+            self.events.push(event("terminated", json!({})));
+        } else {
+            self.running = true;
+        }
+        Ok(json!({ "allThreadsContinued": true }))
+    }
+
+    fn step(&mut self) -> Result<Value, String> {
+        let outcome = self.context.step();
+        self.stopped(outcome, "step");
+        Ok(json!({}))
+    }
+
+    fn pause(&mut self) -> Result<Value, String> {
+        self.running = false;
+        self.events.push(stopped_event("pause", None));
+        Ok(json!({}))
+    }
+
+    /// Reports why the machine stopped after executing with `outcome`, using `reason` if it
+    /// stopped normally.
+    fn stopped(&mut self, outcome: StepOutcome, reason: &str) {
+        let event = match (outcome, self.context.state()) {
+            (StepOutcome::BreakpointHit(_), _) => stopped_event("breakpoint", None),
+            (_, MachineState::Halted) => stopped_event("halt", Some("Halted".to_string())),
+            (_, MachineState::Faulted(fault)) => {
+                stopped_event("exception", Some(fault.to_string()))
+            }
+            (_, MachineState::Running) => stopped_event(reason, None),
+        };
+        self.events.push(event);
+    }
+
+    fn read_memory(&self, request: &Request) -> Result<Value, String> {
+        let arguments: ReadMemoryArguments = request.arguments()?;
+        let start = byte_address(&arguments.memory_reference, arguments.offset)?;
+        let memory = &self.context.memory().0;
+        let end = start
+            .saturating_add(arguments.count)
+            .min(memory.len() * BYTES_PER_WORD);
+        let data: Vec<u8> = (start..end)
+            .map(|byte| {
+                let word = u16::from(memory[byte / BYTES_PER_WORD]);
+                (word >> (8 * (byte % BYTES_PER_WORD))) as u8
+            })
+            .collect();
+        Ok(json!({
+            "address": format!("{:#x}", start),
+            "data": STANDARD.encode(&data),
+            "unreadableBytes": arguments.count - data.len(),
+        }))
+    }
+
+    fn write_memory(&mut self, request: &Request) -> Result<Value, String> {
+        let arguments: WriteMemoryArguments = request.arguments()?;
+        let start = byte_address(&arguments.memory_reference, arguments.offset)?;
+        let data = STANDARD
+            .decode(&arguments.data)
+            .map_err(|err| err.to_string())?;
+        let words = self.context.memory().0.len();
+        match start.checked_add(data.len()) {
+            Some(end) if end <= words * BYTES_PER_WORD => (),
+            _ => return Err("Can't write past the end of memory".to_string()),
+        }
+        for (byte, value) in (start..).zip(&data) {
+            let addr = Address(U12::from_u16((byte / BYTES_PER_WORD) as u16));
+            let shift = 8 * (byte % BYTES_PER_WORD);
+            let word = u16::from(self.context.memory()[addr]) & !(0xff << shift);
+            let word = (word | u16::from(*value) << shift) & 0o7777;
+            self.context.set_addr(addr, U12::from_u16(word));
+        }
+        Ok(json!({ "bytesWritten": data.len() }))
+    }
+
+    /// Converts a line number from the client to a zero-based one.
+    fn source_line(&self, line: usize) -> Option<usize> {
+        line.checked_sub(self.lines_start_at1 as usize)
+    }
+
+    /// Converts a zero-based line number to the client's numbering.
+    fn client_line(&self, line: usize) -> usize {
+        line + self.lines_start_at1 as usize
+    }
+}
+
+fn stopped_event(reason: &str, description: Option<String>) -> Value {
+    event(
+        "stopped",
+        json!({
+            "reason": reason,
+            "description": description,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        }),
+    )
+}
+
+fn variable(name: &str, value: String) -> Value {
+    json!({ "name": name, "value": value, "variablesReference": 0 })
+}
+
+fn memory_reference(addr: Address) -> String {
+    format!("{:#x}", usize::from(addr.0) * BYTES_PER_WORD)
+}
+
+/// Parses a memory reference, as made by [`memory_reference`], and adds `offset` to it.
+fn byte_address(reference: &str, offset: i64) -> Result<usize, String> {
+    let invalid = || format!("Invalid memory reference {}", reference);
+    let base = match reference.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => reference.parse(),
+    }
+    .map_err(|_| invalid())?;
+    i64::try_from(base)
+        .ok()
+        .and_then(|base| base.checked_add(offset))
+        .and_then(|addr| usize::try_from(addr).ok())
+        .ok_or_else(invalid)
+}
+
+/// Parses a value given by the user: a number, which may be in hexadecimal, octal or binary
+/// with a `0x`, `0o` or `0b` prefix, or an address such as `/12`.
+fn parse_word(text: &str) -> Result<U12, String> {
+    let text = text.trim();
+    let digits = text.strip_prefix('/').unwrap_or(text);
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x") => (&digits[2..], 16),
+        Some("0o") => (&digits[2..], 8),
+        Some("0b") => (&digits[2..], 2),
+        _ => (digits, 10),
+    };
+    match u16::from_str_radix(digits, radix) {
+        Ok(value) if value <= 0o7777 => Ok(U12::from_u16(value)),
+        _ => Err(format!("`{}` is not a number between 0 and 4095", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "        LD /ONE\n        ADD /ONE\nloop    BR /loop\nONE     DATA 1\n";
+
+    fn request(command: &str, arguments: Value) -> Request {
+        Request {
+            seq: 1,
+            command: command.to_string(),
+            arguments,
+        }
+    }
+
+    fn launched() -> Session {
+        let mut session = Session::default();
+        session.load("test.sz".to_string(), PROGRAM, false).unwrap();
+        session
+    }
+
+    /// Handles a request, returning its response followed by the events it caused.
+    fn handle(session: &mut Session, command: &str, arguments: Value) -> Vec<Value> {
+        session.handle(request(command, arguments));
+        session.take_messages()
+    }
+
+    #[test]
+    fn stops_at_breakpoints_and_steps() {
+        let mut session = launched();
+        let response = handle(
+            &mut session,
+            "setBreakpoints",
+            json!({ "breakpoints": [{ "line": 3 }, { "line": 5 }] }),
+        );
+        assert_eq!(response[0]["body"]["breakpoints"][0]["verified"], true);
+        assert_eq!(response[0]["body"]["breakpoints"][1]["verified"], false);
+
+        handle(&mut session, "configurationDone", json!({}));
+        assert!(session.is_running());
+        session.run();
+        let stopped = session.take_messages();
+        assert_eq!(stopped[0]["body"]["reason"], "breakpoint");
+        assert_eq!(session.context.pc, Address(U12::from_u16(2)));
+
+        let messages = handle(&mut session, "stackTrace", json!({}));
+        assert_eq!(messages[0]["body"]["stackFrames"][0]["line"], 3);
+        let messages = handle(
+            &mut session,
+            "variables",
+            json!({ "variablesReference": 1 }),
+        );
+        assert_eq!(messages[0]["body"]["variables"][0]["value"], "2");
+
+        let messages = handle(&mut session, "next", json!({}));
+        assert_eq!(messages[1]["body"]["reason"], "step");
+    }
+
+    #[test]
+    fn reads_and_writes_memory() {
+        let mut session = launched();
+        let messages = handle(
+            &mut session,
+            "variables",
+            json!({ "variablesReference": 2 }),
+        );
+        let one = &messages[0]["body"]["variables"][1];
+        assert_eq!(one["name"], "ONE");
+        assert_eq!(one["memoryReference"], "0x6");
+
+        let messages = handle(
+            &mut session,
+            "readMemory",
+            json!({ "memoryReference": "0x6", "count": 2 }),
+        );
+        assert_eq!(messages[0]["body"]["data"], STANDARD.encode([1, 0]));
+
+        handle(
+            &mut session,
+            "writeMemory",
+            json!({ "memoryReference": "0x6", "offset": 1, "data": STANDARD.encode([0xff]) }),
+        );
+        assert_eq!(
+            u16::from(session.context.memory()[Address(U12::from_u16(3))]),
+            0xf01
+        );
+
+        let messages = handle(
+            &mut session,
+            "setVariable",
+            json!({ "variablesReference": 1, "name": "ACC", "value": "0o17" }),
+        );
+        assert_eq!(messages[0]["body"]["value"], "15");
+    }
+
+    #[test]
+    fn rejects_pc_past_memory() {
+        let mut session = launched();
+        let messages = handle(
+            &mut session,
+            "setVariable",
+            json!({ "variablesReference": 1, "name": "PC", "value": "600" }),
+        );
+        assert_eq!(messages[0]["success"], false);
+        assert_eq!(session.context.pc, Address::default());
+
+        let messages = handle(
+            &mut session,
+            "setVariable",
+            json!({ "variablesReference": 1, "name": "PC", "value": "0o777" }),
+        );
+        assert_eq!(messages[0]["success"], true);
+        handle(&mut session, "stackTrace", json!({}));
+    }
+
+    #[test]
+    fn rejects_huge_memory_ranges() {
+        let mut session = launched();
+        let messages = handle(
+            &mut session,
+            "readMemory",
+            json!({ "memoryReference": "0x6", "count": usize::MAX }),
+        );
+        assert_eq!(messages[0]["success"], true);
+        let messages = handle(
+            &mut session,
+            "readMemory",
+            json!({ "memoryReference": "0x6", "offset": i64::MAX, "count": 1 }),
+        );
+        assert_eq!(messages[0]["success"], false);
+        let messages = handle(
+            &mut session,
+            "writeMemory",
+            json!({ "memoryReference": format!("{:#x}", usize::MAX), "data": "AA" }),
+        );
+        assert_eq!(messages[0]["success"], false);
+    }
+}
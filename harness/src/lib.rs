@@ -0,0 +1,341 @@
+//! Runs programs against test cases written in TOML and reports the results, for grading many
+//! submissions of the same exercise. See [`Spec`] for the format of the cases.
+
+use simplez_common::{DebugInfo, Memory};
+use simplez_interpreter::{ExecutionContext, MachineState};
+use twelve_bit::u12::U12;
+
+pub mod report;
+pub mod spec;
+
+pub use spec::{Case, Spec};
+
+/// The results of running every case of a spec on one program.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SuiteResult {
+    /// The name of the program, usually its path.
+    pub program: String,
+    pub cases: Vec<CaseResult>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    /// How many instructions the program executed.
+    pub steps: u64,
+    #[serde(flatten)]
+    pub status: Status,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Status {
+    Passed,
+    /// The program ran but didn't leave the machine as expected.
+    Failed {
+        mismatches: Vec<Mismatch>,
+    },
+    /// The case couldn't be run, for example because the program doesn't assemble or the case
+    /// refers to a label the program doesn't define.
+    Error {
+        message: String,
+    },
+}
+
+/// Something about the machine that wasn't as expected.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct Mismatch {
+    /// What was checked, such as `ACC` or `suma (/6)`.
+    pub what: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl SuiteResult {
+    pub fn passed(&self) -> usize {
+        self.count(|status| matches!(status, Status::Passed))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, Status::Failed { .. }))
+    }
+
+    pub fn errors(&self) -> usize {
+        self.count(|status| matches!(status, Status::Error { .. }))
+    }
+
+    fn count(&self, f: impl Fn(&Status) -> bool) -> usize {
+        self.cases.iter().filter(|case| f(&case.status)).count()
+    }
+}
+
+/// Assembles `source` and runs every case of `spec` on it, each on a freshly reset machine.
+pub fn run_suite(spec: &Spec, program: &str, source: &str) -> SuiteResult {
+    let assembled = assemble(source);
+    let cases = spec
+        .cases
+        .iter()
+        .map(|case| match &assembled {
+            Ok((memory, debug_info)) => {
+                run_case(case, spec.max_steps, memory.clone(), debug_info.clone())
+            }
+            Err(message) => error(case, message.clone()),
+        })
+        .collect();
+    SuiteResult {
+        program: program.to_string(),
+        cases,
+    }
+}
+
+/// Runs a program that checks itself with `;@expect` comments, without any cases to set it up.
+/// The result has a single case, which fails if the program doesn't halt within `max_steps` or
+/// any of its assertions doesn't hold.
+pub fn run_assertions(program: &str, source: &str, max_steps: usize) -> SuiteResult {
+    let case = Case {
+        name: "Inline assertions".to_string(),
+        expect: spec::Expectations {
+            assertions: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = match assemble(source) {
+        Ok((_, debug_info)) if debug_info.assertions().is_empty() => error(
+            &case,
+            "The program has no `;@expect` assertions".to_string(),
+        ),
+        Ok((memory, debug_info)) => run_case(&case, max_steps, memory, debug_info),
+        Err(message) => error(&case, message),
+    };
+    SuiteResult {
+        program: program.to_string(),
+        cases: vec![result],
+    }
+}
+
+fn assemble(source: &str) -> Result<(Memory, DebugInfo), String> {
+    simplez_assembler::assemble_with_debug_info(source)
+        .map_err(|err| format!("The program doesn't assemble: {}", err.kind))
+}
+
+fn error(case: &Case, message: String) -> CaseResult {
+    CaseResult {
+        name: case.name.clone(),
+        steps: 0,
+        status: Status::Error { message },
+    }
+}
+
+/// Runs a single case on an assembled program. `max_steps` is used unless the case sets its own.
+pub fn run_case(
+    case: &Case,
+    max_steps: usize,
+    memory: Memory,
+    debug_info: DebugInfo,
+) -> CaseResult {
+    let mut context = ExecutionContext::default();
+    context.set_memory(memory);
+    context.set_debug_info(debug_info);
+
+    let status = match setup(case, &mut context) {
+        Ok(()) => {
+            context.run(case.max_steps.unwrap_or(max_steps));
+            check(case, &context)
+        }
+        Err(message) => Status::Error { message },
+    };
+    CaseResult {
+        name: case.name.clone(),
+        steps: context.instructions_executed(),
+        status,
+    }
+}
+
+fn setup(case: &Case, context: &mut ExecutionContext) -> Result<(), String> {
+    for (name, value) in &case.memory {
+        let addr = spec::resolve(name, context.debug_info())?;
+        context.set_addr(addr, spec::word(*value)?);
+    }
+    if let Some(acc) = case.registers.acc {
+        context.acc = spec::word(acc)?;
+    }
+    if let Some(pc) = case.registers.pc {
+        context.pc = spec::resolve(&format!("/{}", pc), context.debug_info())?;
+    }
+    Ok(())
+}
+
+/// Compares the machine with what `case` expects, returning every difference.
+fn check(case: &Case, context: &ExecutionContext) -> Status {
+    let expect = &case.expect;
+    let mut mismatches = Vec::new();
+    let halted = context.state() == MachineState::Halted;
+    if expect.halted != halted {
+        mismatches.push(Mismatch {
+            what: "State".to_string(),
+            expected: if expect.halted {
+                MachineState::Halted.to_string()
+            } else {
+                "Not halted".to_string()
+            },
+            actual: match context.state() {
+                MachineState::Running => "Still running after the step budget".to_string(),
+                state => state.to_string(),
+            },
+        });
+    }
+
+    if expect.assertions && halted {
+        mismatches.extend(context.failed_assertions().into_iter().map(|failed| {
+            let assertion = failed.assertion;
+            Mismatch {
+                what: format!(
+                    "`;@expect {}` on line {}",
+                    assertion.name,
+                    assertion.line + 1
+                ),
+                expected: describe(assertion.expected),
+                actual: describe(failed.actual),
+            }
+        }));
+    }
+
+    let mut compare = |what: String, expected: i32, actual: U12| -> Result<(), String> {
+        let expected = spec::word(expected)?;
+        if expected != actual {
+            mismatches.push(Mismatch {
+                what,
+                expected: describe(expected),
+                actual: describe(actual),
+            });
+        }
+        Ok(())
+    };
+    let result = (|| {
+        if let Some(acc) = expect.registers.acc {
+            compare("ACC".to_string(), acc, context.acc)?;
+        }
+        if let Some(pc) = expect.registers.pc {
+            compare("PC".to_string(), pc, context.pc.0)?;
+        }
+        for (name, value) in &expect.memory {
+            let addr = spec::resolve(name, context.debug_info())?;
+            let what = if name.starts_with('/') {
+                name.clone()
+            } else {
+                format!("{} ({})", name, addr)
+            };
+            compare(what, *value, context.memory()[addr])?;
+        }
+        Ok(())
+    })();
+
+    match result {
+        Err(message) => Status::Error { message },
+        Ok(()) if mismatches.is_empty() => Status::Passed,
+        Ok(()) => Status::Failed { mismatches },
+    }
+}
+
+/// Shows a word both unsigned and, if it is negative in two's complement, signed.
+fn describe(word: U12) -> String {
+    let value = u16::from(word);
+    if value >= 2048 {
+        format!("{} ({})", value, value as i32 - 4096)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "        LD /a\n        ADD /b\n        ST /sum\n        HALT\n\
+                           a       DATA 0\nb       DATA 0\nsum     RES 1\n";
+
+    #[test]
+    fn runs_cases() {
+        let spec = Spec::parse(
+            r#"
+            [[case]]
+            name = "adds"
+            memory = { a = 2, b = 3 }
+            expect.memory = { sum = 5 }
+
+            [[case]]
+            name = "negative"
+            memory = { a = -1, "/5" = -1 }
+            expect = { memory = { sum = -2 }, registers = { acc = 7 } }
+
+            [[case]]
+            name = "stuck"
+            max_steps = 2
+            expect.memory = { total = 1 }
+            "#,
+        )
+        .unwrap();
+        let suite = run_suite(&spec, "add.sz", PROGRAM);
+        assert_eq!(suite.cases[0].status, Status::Passed);
+        assert_eq!(suite.cases[0].steps, 4);
+        assert_eq!(
+            suite.cases[1].status,
+            Status::Failed {
+                mismatches: vec![Mismatch {
+                    what: "ACC".to_string(),
+                    expected: "7".to_string(),
+                    actual: "4094 (-2)".to_string(),
+                }]
+            }
+        );
+        assert!(matches!(suite.cases[2].status, Status::Error { .. }));
+        assert_eq!((suite.passed(), suite.failed(), suite.errors()), (1, 1, 1));
+
+        let broken = run_suite(&spec, "broken.sz", "        FOO\n");
+        assert_eq!(broken.errors(), 3);
+        let out_of_range = run_suite(&spec, "range.sz", "        LD /99999\n        HALT\n");
+        assert!(out_of_range
+            .cases
+            .iter()
+            .all(|case| matches!(case.status, Status::Error { .. })));
+    }
+
+    #[test]
+    fn checks_inline_assertions() {
+        let source = format!("        CLR ;@expect sum = 5, ACC = 5\n{}", PROGRAM);
+        let suite = run_assertions("add.sz", &source, 100);
+        assert_eq!(
+            suite.cases[0].status,
+            Status::Failed {
+                mismatches: vec![
+                    Mismatch {
+                        what: "`;@expect sum` on line 1".to_string(),
+                        expected: "5".to_string(),
+                        actual: "0".to_string(),
+                    },
+                    Mismatch {
+                        what: "`;@expect ACC` on line 1".to_string(),
+                        expected: "5".to_string(),
+                        actual: "0".to_string(),
+                    }
+                ]
+            }
+        );
+
+        let spec = Spec::parse(
+            r#"
+            [[case]]
+            name = "adds"
+            memory = { a = 2, b = 3 }
+            expect.assertions = true
+
+            [[case]]
+            name = "ignores assertions"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(run_suite(&spec, "add.sz", &source).passed(), 2);
+        assert_eq!(run_assertions("add.sz", PROGRAM, 100).errors(), 1);
+    }
+}
@@ -0,0 +1,135 @@
+//! Writes the results of test runs for people and for CI systems.
+
+use std::fmt::Write;
+
+use crate::{Status, SuiteResult};
+
+/// Describes what went wrong in a case that didn't pass, one line per difference.
+pub fn diff(status: &Status) -> Vec<String> {
+    match status {
+        Status::Passed => Vec::new(),
+        Status::Failed { mismatches } => mismatches
+            .iter()
+            .map(|mismatch| {
+                format!(
+                    "{}: expected {}, got {}",
+                    mismatch.what, mismatch.expected, mismatch.actual
+                )
+            })
+            .collect(),
+        Status::Error { message } => vec![message.clone()],
+    }
+}
+
+/// Writes results in the JUnit XML format most CI systems understand, with a test suite for
+/// each program.
+pub fn junit_xml(suites: &[SuiteResult]) -> String {
+    let total = |f: fn(&SuiteResult) -> usize| suites.iter().map(f).sum::<usize>();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\">",
+        total(|suite| suite.cases.len()),
+        total(SuiteResult::failed),
+        total(SuiteResult::errors)
+    );
+    for suite in suites {
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">",
+            escape(&suite.program),
+            suite.cases.len(),
+            suite.failed(),
+            suite.errors()
+        );
+        for case in &suite.cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape(&case.name),
+                escape(&suite.program)
+            );
+            let element = match &case.status {
+                Status::Passed => {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+                Status::Failed { .. } => "failure",
+                Status::Error { .. } => "error",
+            };
+            let lines = diff(&case.status);
+            let _ = writeln!(
+                xml,
+                ">\n      <{} message=\"{}\">{}</{}>\n    </testcase>",
+                element,
+                escape(&lines[0]),
+                escape(&lines.join("\n")),
+                element
+            );
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Writes results as a JSON array with an object for each program.
+pub fn json(suites: &[SuiteResult]) -> String {
+    serde_json::to_string_pretty(suites).expect("results are always serializable")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaseResult, Mismatch};
+
+    #[test]
+    fn formats() {
+        let suites = [SuiteResult {
+            program: "a<b>.sz".to_string(),
+            cases: vec![
+                CaseResult {
+                    name: "ok".to_string(),
+                    steps: 3,
+                    status: Status::Passed,
+                },
+                CaseResult {
+                    name: "sum".to_string(),
+                    steps: 4,
+                    status: Status::Failed {
+                        mismatches: vec![Mismatch {
+                            what: "sum (/6)".to_string(),
+                            expected: "5".to_string(),
+                            actual: "\"4\"".to_string(),
+                        }],
+                    },
+                },
+            ],
+        }];
+        assert_eq!(
+            junit_xml(&suites),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites tests=\"2\" failures=\"1\" errors=\"0\">\n  \
+             <testsuite name=\"a&lt;b&gt;.sz\" tests=\"2\" failures=\"1\" errors=\"0\">\n    \
+             <testcase name=\"ok\" classname=\"a&lt;b&gt;.sz\"/>\n    \
+             <testcase name=\"sum\" classname=\"a&lt;b&gt;.sz\">\n      \
+             <failure message=\"sum (/6): expected 5, got &quot;4&quot;\">\
+             sum (/6): expected 5, got &quot;4&quot;</failure>\n    \
+             </testcase>\n  \
+             </testsuite>\n\
+             </testsuites>\n"
+        );
+
+        let value: serde_json::Value = serde_json::from_str(&json(&suites)).unwrap();
+        assert_eq!(value[0]["cases"][0]["status"], "passed");
+        assert_eq!(value[0]["cases"][1]["mismatches"][0]["expected"], "5");
+    }
+}
@@ -0,0 +1,129 @@
+//! The TOML format test cases are written in.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use simplez_common::{Address, DebugInfo};
+use twelve_bit::u12::U12;
+
+/// How many instructions a case may execute if neither it nor its spec say otherwise.
+pub const DEFAULT_MAX_STEPS: usize = 100_000;
+
+/// A set of test cases for a program, such as:
+///
+/// ```toml
+/// program = "fib.sz"
+///
+/// [[case]]
+/// name = "Adds the first 6 terms"
+/// memory = { k = 6 }
+/// expect = { memory = { suma = 20 }, assertions = true }
+///
+/// [[case]]
+/// name = "Nothing to add"
+/// max_steps = 100
+/// memory = { k = 0 }
+/// expect = { memory = { suma = 0 }, registers = { acc = 0 } }
+/// ```
+///
+/// Words are named by label or by address, such as `"/12"`. Their values can be negative, in
+/// which case they are stored in two's complement.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    /// The program to test, relative to the spec, if it isn't given some other way.
+    pub program: Option<PathBuf>,
+    /// How many instructions each case may execute before it is considered stuck.
+    #[serde(default = "default_max_steps")]
+    pub max_steps: usize,
+    #[serde(default, rename = "case")]
+    pub cases: Vec<Case>,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub name: String,
+    /// Overrides [`Spec::max_steps`] for this case.
+    pub max_steps: Option<usize>,
+    /// Words to set before running the program, by label or address.
+    #[serde(default)]
+    pub memory: BTreeMap<String, i32>,
+    /// Registers to set before running the program.
+    #[serde(default)]
+    pub registers: Registers,
+    #[serde(default)]
+    pub expect: Expectations,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Registers {
+    pub acc: Option<i32>,
+    pub pc: Option<i32>,
+}
+
+/// What the machine must look like once the program stops.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectations {
+    /// Whether the program must reach a `HALT` within the step budget.
+    #[serde(default = "default_halted")]
+    pub halted: bool,
+    /// Whether the `;@expect` assertions of the program must hold once it halts. Programs
+    /// usually assert what they do with their initial data, so this is off by default.
+    #[serde(default)]
+    pub assertions: bool,
+    #[serde(default)]
+    pub memory: BTreeMap<String, i32>,
+    #[serde(default)]
+    pub registers: Registers,
+}
+
+impl Default for Expectations {
+    fn default() -> Self {
+        Self {
+            halted: true,
+            assertions: false,
+            memory: BTreeMap::new(),
+            registers: Registers::default(),
+        }
+    }
+}
+
+impl Spec {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+}
+
+fn default_max_steps() -> usize {
+    DEFAULT_MAX_STEPS
+}
+
+fn default_halted() -> bool {
+    true
+}
+
+/// Finds the word `name` refers to: a label of the program or an address such as `/12`.
+pub fn resolve(name: &str, debug_info: &DebugInfo) -> Result<Address, String> {
+    if let Some(address) = name.strip_prefix('/') {
+        return match address.parse::<u16>() {
+            Ok(address) if address < 512 => Ok(Address(U12::from_u16(address))),
+            _ => Err(format!("`{}` is not an address between /0 and /511", name)),
+        };
+    }
+    debug_info
+        .symbol(name)
+        .map(|symbol| symbol.address)
+        .ok_or_else(|| format!("The program doesn't define the label `{}`", name))
+}
+
+/// Converts the value of a word in a spec into a word, wrapping negative values around.
+pub fn word(value: i32) -> Result<U12, String> {
+    if (-2048..=4095).contains(&value) {
+        Ok(U12::from_u16((value & 0o7777) as u16))
+    } else {
+        Err(format!("{} doesn't fit in a word", value))
+    }
+}
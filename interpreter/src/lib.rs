@@ -1,21 +1,156 @@
-use std::{collections::VecDeque, ops::ControlFlow};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::Display;
 
 use simplez_common::*;
 use twelve_bit::u12;
 use twelve_bit::u12::*;
 
+pub mod profile;
+pub mod snapshot;
+pub mod trace;
+
+use profile::Profile;
+use trace::{MemoryWrite, TraceEntry, Tracer};
+
+/// How many addresses [`ExecutionContext::last_modifications`] remembers.
+pub const TRACKED_MODIFICATIONS: usize = 5;
+
+/// The result of executing instructions on an [`ExecutionContext`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StepOutcome {
+    /// The instruction was executed and nothing out of the ordinary happened.
+    Continue,
+    /// A `HALT` instruction was executed, or the machine had already halted.
+    Halted,
+    /// Execution stopped right before the instruction at a breakpoint.
+    BreakpointHit(Address),
+    /// [`ExecutionContext::run`] executed as many instructions as it was allowed to.
+    StepLimitReached,
+    /// The word executed was assembled from a `DATA` directive and hasn't been overwritten by the
+    /// program since.
+    ExecutedDataWord(Address),
+    /// The program counter went past the end of memory and wrapped back to `/0`.
+    PcWrappedAround,
+    /// A `ST` instruction overwrote a word assembled from an instruction.
+    WroteToCode(Address),
+    /// The program halted but the `;@expect` assertion on the given zero-based line of its
+    /// source doesn't hold.
+    AssertionFailed { line: usize },
+}
+
+impl Display for StepOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepOutcome::Continue => f.write_str("Running"),
+            StepOutcome::Halted => f.write_str("Halted"),
+            StepOutcome::BreakpointHit(addr) => {
+                f.write_fmt(format_args!("Breakpoint hit at [{}]", addr))
+            }
+            StepOutcome::StepLimitReached => f.write_str("Step limit reached"),
+            StepOutcome::ExecutedDataWord(addr) => {
+                f.write_fmt(format_args!("Executed a DATA word at [{}]", addr))
+            }
+            StepOutcome::PcWrappedAround => f.write_str("PC wrapped around to [/0]"),
+            StepOutcome::WroteToCode(addr) => {
+                f.write_fmt(format_args!("Overwrote the instruction at [{}]", addr))
+            }
+            StepOutcome::AssertionFailed { line } => {
+                f.write_fmt(format_args!("Assertion on line {} failed", line + 1))
+            }
+        }
+    }
+}
+
+/// An `;@expect` assertion that doesn't hold, as found by
+/// [`ExecutionContext::failed_assertions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailedAssertion {
+    pub assertion: Assertion,
+    /// The value of the assertion's target.
+    pub actual: U12,
+}
+
+/// Whether an [`ExecutionContext`] can keep executing instructions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MachineState {
+    #[default]
+    Running,
+    /// A `HALT` instruction has been executed.
+    Halted,
+    /// A trapped condition (see [`Traps`]) stopped the machine.
+    Faulted(StepOutcome),
+}
+
+impl Display for MachineState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MachineState::Running => f.write_str("Running"),
+            MachineState::Halted => f.write_str("Halted"),
+            MachineState::Faulted(reason) => f.write_fmt(format_args!("Faulted: {}", reason)),
+        }
+    }
+}
+
+/// Selects which unusual conditions fault the machine. Conditions that are not trapped are
+/// still reported by [`ExecutionContext::step`], but execution carries on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Traps {
+    pub executed_data_word: bool,
+    pub pc_wrapped_around: bool,
+    /// Self-modifying code is common in Simplez programs, so this is off by default.
+    pub wrote_to_code: bool,
+    /// Checks the program's `;@expect` assertions when it halts, faulting if any doesn't hold.
+    /// This is the test mode for self-checking exercises, so it is off by default.
+    pub failed_assertion: bool,
+}
+
+impl Default for Traps {
+    fn default() -> Self {
+        Self {
+            executed_data_word: true,
+            pc_wrapped_around: true,
+            wrote_to_code: false,
+            failed_assertion: false,
+        }
+    }
+}
+
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct ExecutionContext {
-    #[serde(skip)]
+    #[serde(default)]
     pub acc: U12,
-    #[serde(skip)]
+    #[serde(default)]
     pub pc: Address,
-    #[serde(skip)]
+    #[serde(default)]
     pub ir: U12,
     memory: Memory,
-    #[serde(skip)]
-    /// A list of the latest modified addresses.
+    #[serde(default)]
+    /// A list of the latest modified addresses, most recent first.
     last_modifications: VecDeque<Address>,
+    #[serde(default)]
+    debug_info: DebugInfo,
+    #[serde(default)]
+    state: MachineState,
+    #[serde(default)]
+    pub traps: Traps,
+    #[serde(default)]
+    pub breakpoints: BTreeSet<Address>,
+    #[serde(default)]
+    /// The breakpoint [`Self::run`] last stopped at, so that resuming doesn't stop there again.
+    paused_at_breakpoint: Option<Address>,
+    #[serde(default)]
+    /// Addresses the program has stored to since its memory was set.
+    stored_to: BTreeSet<Address>,
+    #[serde(default)]
+    /// Instructions executed since the registers were last reset.
+    instructions_executed: u64,
+    #[serde(skip)]
+    /// How often each word was used since the registers were last reset.
+    profile: Profile,
+    #[serde(skip)]
+    /// Records every step executed while it is set.
+    pub tracer: Option<Tracer>,
 }
 
 impl Default for ExecutionContext {
@@ -26,45 +161,181 @@ impl Default for ExecutionContext {
             ir: u12!(0),
             memory: Default::default(),
             last_modifications: Default::default(),
+            debug_info: Default::default(),
+            state: Default::default(),
+            traps: Default::default(),
+            breakpoints: Default::default(),
+            paused_at_breakpoint: None,
+            stored_to: Default::default(),
+            instructions_executed: 0,
+            profile: Default::default(),
+            tracer: None,
         }
     }
 }
 
 impl ExecutionContext {
     /// Steps the Simplez execution context by one instruction.
-    pub fn step(&mut self) -> ControlFlow<(), ()> {
+    ///
+    /// Does nothing if the machine is not [`MachineState::Running`], returning the reason why it
+    /// stopped instead. Breakpoints are ignored; see [`Self::run`].
+    pub fn step(&mut self) -> StepOutcome {
+        let (pc, acc_before, executed) = (self.pc, self.acc, self.instructions_executed);
+        let stored = match Instruction::from(self.memory[pc]) {
+            Instruction::Store { address } => Some((address, self.memory[address])),
+            _ => None,
+        };
+        let outcome = self.execute();
+        if self.instructions_executed == executed {
+            return outcome;
+        }
+
+        let instruction = Instruction::from(self.ir);
+        self.profile.record(pc, instruction);
+        if let Some(tracer) = &mut self.tracer {
+            tracer.record(TraceEntry {
+                step: self.instructions_executed,
+                pc,
+                ir: self.ir,
+                instruction,
+                acc_before,
+                acc_after: self.acc,
+                write: stored.map(|(address, before)| MemoryWrite {
+                    address,
+                    before,
+                    after: self.memory[address],
+                }),
+            });
+        }
+        outcome
+    }
+
+    fn execute(&mut self) -> StepOutcome {
+        match self.state {
+            MachineState::Running => (),
+            MachineState::Halted => return StepOutcome::Halted,
+            MachineState::Faulted(reason) => return reason,
+        }
+        self.paused_at_breakpoint = None;
+
+        let mut outcome = StepOutcome::Continue;
+        if self.debug_info.word_kind(self.pc) == Some(WordKind::Data)
+            && !self.stored_to.contains(&self.pc)
+        {
+            let trapped = self.traps.executed_data_word;
+            self.report(
+                &mut outcome,
+                StepOutcome::ExecutedDataWord(self.pc),
+                trapped,
+            );
+            if trapped {
+                return outcome;
+            }
+        }
+
         self.ir = self.memory[self.pc];
+        self.instructions_executed += 1;
         match Instruction::from(self.ir) {
-            Instruction::Store { address } => self.set_addr(address, self.acc),
+            Instruction::Store { address } => {
+                self.set_addr(address, self.acc);
+                self.stored_to.insert(address);
+                if self.debug_info.word_kind(address) == Some(WordKind::Instruction) {
+                    let trapped = self.traps.wrote_to_code;
+                    self.report(&mut outcome, StepOutcome::WroteToCode(address), trapped);
+                }
+            }
             Instruction::Load { address } => self.acc = self.memory[address],
             Instruction::Add { address } => {
                 self.acc += self.memory[address];
             }
             Instruction::Branch { address } => {
                 self.pc = address;
-                return ControlFlow::Continue(());
+                return outcome;
             }
             Instruction::BranchIfZero { address } => {
                 if self.zero() {
                     self.pc = address;
-                    return ControlFlow::Continue(());
+                    return outcome;
                 }
             }
             Instruction::Clear => self.acc = u12!(0),
             Instruction::Decrease => {
                 self.acc -= u12!(1);
             }
-            Instruction::Halt => return ControlFlow::Break(()),
+            Instruction::Halt => {
+                self.state = MachineState::Halted;
+                if self.traps.failed_assertion {
+                    if let Some(failed) = self.failed_assertions().first() {
+                        let line = failed.assertion.line;
+                        self.report(&mut outcome, StepOutcome::AssertionFailed { line }, true);
+                        return outcome;
+                    }
+                }
+                return StepOutcome::Halted;
+            }
         }
         self.pc.0 += u12!(1);
+        if usize::from(self.pc.0) >= self.memory.0.len() {
+            self.pc = Address::default();
+            let trapped = self.traps.pc_wrapped_around;
+            self.report(&mut outcome, StepOutcome::PcWrappedAround, trapped);
+        }
 
-        ControlFlow::Continue(())
+        outcome
+    }
+
+    /// Executes instructions until the machine stops, a breakpoint is reached or `max_steps`
+    /// instructions have been executed, whichever happens first.
+    ///
+    /// Conditions that are not trapped do not stop execution and are not reported.
+    pub fn run(&mut self, max_steps: usize) -> StepOutcome {
+        for _ in 0..max_steps {
+            if self.breakpoints.contains(&self.pc) && self.paused_at_breakpoint != Some(self.pc) {
+                self.paused_at_breakpoint = Some(self.pc);
+                return StepOutcome::BreakpointHit(self.pc);
+            }
+            let outcome = self.step();
+            if self.state != MachineState::Running {
+                return outcome;
+            }
+        }
+
+        StepOutcome::StepLimitReached
+    }
+
+    /// Records `condition` as the outcome of the current step, faulting the machine if it is
+    /// `trapped`. Trapped conditions take precedence over those that aren't.
+    fn report(&mut self, outcome: &mut StepOutcome, condition: StepOutcome, trapped: bool) {
+        if trapped {
+            self.state = MachineState::Faulted(condition);
+            *outcome = condition;
+        } else if *outcome == StepOutcome::Continue {
+            *outcome = condition;
+        }
     }
 
     pub fn reset_registers(&mut self) {
         self.acc = Default::default();
         self.pc = Default::default();
         self.ir = Default::default();
+        self.state = MachineState::Running;
+        self.paused_at_breakpoint = None;
+        self.instructions_executed = 0;
+        self.profile.clear();
+    }
+
+    pub fn state(&self) -> MachineState {
+        self.state
+    }
+
+    /// The number of instructions executed since the registers were last reset.
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions_executed
+    }
+
+    /// How often each word was executed, read and written since the registers were last reset.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
     }
 
     pub fn memory(&self) -> &Memory {
@@ -74,11 +345,53 @@ impl ExecutionContext {
     pub fn set_addr(&mut self, addr: Address, val: U12) {
         self.memory[addr] = val;
         self.last_modifications.push_front(addr);
+        self.last_modifications.truncate(TRACKED_MODIFICATIONS);
     }
 
     pub fn set_memory(&mut self, mem: Memory) {
         self.memory = mem;
         self.last_modifications.clear();
+        self.stored_to.clear();
+    }
+
+    /// Replaces the program with one assembled from an edited version of its source, keeping the
+    /// registers and machine state so that execution can carry on where it left off.
+    ///
+    /// If `keep_data` is set, words assembled from `DATA` or `RES` in both programs keep the
+    /// values they have now instead of being reset to the ones in `memory`.
+    pub fn reload_program(&mut self, mut memory: Memory, debug_info: DebugInfo, keep_data: bool) {
+        let is_data = |kind| matches!(kind, Some(WordKind::Data) | Some(WordKind::Reserved));
+        let mut kept = BTreeSet::new();
+        if keep_data {
+            for addr in 0..memory.0.len() {
+                let addr = Address(U12::from_u16(addr as u16));
+                if is_data(self.debug_info.word_kind(addr)) && is_data(debug_info.word_kind(addr)) {
+                    memory[addr] = self.memory[addr];
+                    kept.insert(addr);
+                }
+            }
+        }
+        self.memory = memory;
+        self.debug_info = debug_info;
+        self.stored_to.retain(|addr| kept.contains(addr));
+    }
+
+    pub fn debug_info(&self) -> &DebugInfo {
+        &self.debug_info
+    }
+
+    pub fn set_debug_info(&mut self, debug_info: DebugInfo) {
+        self.debug_info = debug_info;
+    }
+
+    /// Toggles the breakpoint at `addr`, returning whether it is now set.
+    pub fn toggle_breakpoint(&mut self, addr: Address) -> bool {
+        if self.breakpoints.remove(&addr) {
+            false
+        } else {
+            self.breakpoints.insert(addr);
+            true
+        }
     }
 
     /// The zero bit register. Only set to true if `self.acc == 0`.
@@ -89,4 +402,220 @@ impl ExecutionContext {
     pub fn last_modifications(&self) -> &VecDeque<Address> {
         &self.last_modifications
     }
+
+    /// Checks the `;@expect` assertions of the program against the machine as it is now,
+    /// returning those that don't hold.
+    pub fn failed_assertions(&self) -> Vec<FailedAssertion> {
+        self.debug_info
+            .assertions()
+            .iter()
+            .filter_map(|assertion| {
+                let actual = match assertion.target {
+                    AssertionTarget::Acc => self.acc,
+                    AssertionTarget::Word(addr) => self.memory[addr],
+                };
+                (actual != assertion.expected).then(|| FailedAssertion {
+                    assertion: assertion.clone(),
+                    actual,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context_with(words: &[u16]) -> ExecutionContext {
+        let mut memory = Memory::default();
+        for (addr, word) in words.iter().enumerate() {
+            memory.0[addr] = U12::from_u16(*word);
+        }
+        let mut context = ExecutionContext::default();
+        context.set_memory(memory);
+        context
+    }
+
+    #[test]
+    fn halt_stops_the_machine() {
+        // CLR, HALT
+        let mut context = context_with(&[5 << 9, 7 << 9]);
+        assert_eq!(context.run(10), StepOutcome::Halted);
+        assert_eq!(context.state(), MachineState::Halted);
+        assert_eq!(context.pc, Address(u12!(1)));
+        assert_eq!(context.step(), StepOutcome::Halted);
+        assert_eq!(context.pc, Address(u12!(1)));
+
+        context.reset_registers();
+        assert_eq!(context.state(), MachineState::Running);
+    }
+
+    #[test]
+    fn breakpoints_stop_once_per_visit() {
+        // loop: CLR, BR /loop
+        let mut context = context_with(&[5 << 9, 3 << 9]);
+        context.toggle_breakpoint(Address(u12!(1)));
+        assert_eq!(
+            context.run(10),
+            StepOutcome::BreakpointHit(Address(u12!(1)))
+        );
+        assert_eq!(
+            context.run(10),
+            StepOutcome::BreakpointHit(Address(u12!(1)))
+        );
+        assert_eq!(context.run(1), StepOutcome::StepLimitReached);
+    }
+
+    #[test]
+    fn running_off_the_end_of_memory_wraps_around() {
+        let mut context = context_with(&[]);
+        context.pc = Address(u12!(511));
+        // Memory is all zeroes, so this executes ST /0.
+        assert_eq!(context.step(), StepOutcome::PcWrappedAround);
+        assert_eq!(context.pc, Address(u12!(0)));
+        assert_eq!(
+            context.state(),
+            MachineState::Faulted(StepOutcome::PcWrappedAround)
+        );
+    }
+
+    #[test]
+    fn reloading_keeps_registers_and_optionally_data() {
+        // CLR, ST /2, DATA 7
+        let mut context = context_with(&[5 << 9, 2, 7]);
+        let mut debug_info = DebugInfo::default();
+        for (addr, kind) in [WordKind::Instruction, WordKind::Instruction, WordKind::Data]
+            .into_iter()
+            .enumerate()
+        {
+            debug_info.set_word_kind(Address(U12::from_u16(addr as u16)), kind);
+            debug_info.set_source_line(Address(U12::from_u16(addr as u16)), addr);
+        }
+        context.set_debug_info(debug_info.clone());
+        context.run(2);
+        assert_eq!(context.memory()[Address(u12!(2))], u12!(0));
+
+        // DEC, ST /2, DATA 9
+        let mut memory = Memory::default();
+        memory.0[0] = U12::from_u16(6 << 9);
+        memory.0[1] = U12::from_u16(2);
+        memory.0[2] = U12::from_u16(9);
+        context.reload_program(memory.clone(), debug_info.clone(), true);
+        assert_eq!(context.pc, Address(u12!(2)));
+        assert_eq!(context.memory()[Address(u12!(0))], U12::from_u16(6 << 9));
+        assert_eq!(context.memory()[Address(u12!(2))], u12!(0));
+
+        context.reload_program(memory, debug_info, false);
+        assert_eq!(context.pc, Address(u12!(2)));
+        assert_eq!(context.memory()[Address(u12!(2))], u12!(9));
+    }
+
+    #[test]
+    fn test_mode_checks_assertions_at_halt() {
+        // CLR, HALT
+        let mut context = context_with(&[5 << 9, 7 << 9]);
+        let mut debug_info = DebugInfo::default();
+        debug_info.add_assertion(Assertion {
+            name: "ACC".to_string(),
+            target: AssertionTarget::Acc,
+            expected: u12!(1),
+            line: 3,
+        });
+        context.set_debug_info(debug_info);
+        assert_eq!(context.run(10), StepOutcome::Halted);
+        assert_eq!(context.failed_assertions()[0].actual, u12!(0));
+
+        context.reset_registers();
+        context.traps.failed_assertion = true;
+        assert_eq!(context.run(10), StepOutcome::AssertionFailed { line: 3 });
+        assert_eq!(
+            context.state(),
+            MachineState::Faulted(StepOutcome::AssertionFailed { line: 3 })
+        );
+    }
+
+    #[test]
+    fn tracer_records_steps() {
+        // DEC, ST /3, HALT
+        let mut context = context_with(&[6 << 9, 3, 7 << 9]);
+        context.tracer = Some(Tracer::new(2));
+        context.run(10);
+        let tracer = context.tracer.as_ref().unwrap();
+        assert_eq!(tracer.dropped(), 1);
+        assert_eq!(
+            tracer.to_csv(),
+            "step,pc,ir,instruction,acc_before,acc_after,write_address,write_before,write_after\n\
+             2,1,3,ST /3,4095,4095,3,0,4095\n\
+             3,2,3584,HALT,4095,4095,,,\n"
+        );
+        assert_eq!(
+            tracer.to_json_lines().lines().next(),
+            Some(
+                "{\"step\":2,\"pc\":1,\"ir\":3,\"instruction\":\"ST /3\",\"acc_before\":4095,\
+                 \"acc_after\":4095,\"write\":{\"address\":3,\"before\":0,\"after\":4095}}"
+            )
+        );
+        let filtered = |filter| {
+            tracer
+                .entries()
+                .iter()
+                .filter(|entry| entry.matches(filter))
+                .count()
+        };
+        assert_eq!(
+            (filtered("/3"), filtered("halt"), filtered("LD")),
+            (1, 1, 0)
+        );
+    }
+
+    #[test]
+    fn profile_counts_uses_of_each_word() {
+        // loop: LD /4, BZ /3, BR /0, HALT, DATA 0
+        let mut context = context_with(&[1 << 9 | 4, 4 << 9 | 3, 3 << 9, 7 << 9]);
+        context.set_addr(Address(u12!(4)), u12!(0));
+        context.run(10);
+        let profile = context.profile();
+        let counts = |addr: u16| profile.counts(Address(U12::from_u16(addr)));
+        assert_eq!(counts(0).executions, 1);
+        assert_eq!(counts(4).reads, 1);
+        assert_eq!(counts(2).total(), 0);
+        assert_eq!(profile.totals().executions, context.instructions_executed());
+        assert_eq!(profile.hottest()[0].0, Address(u12!(0)));
+
+        context.reset_registers();
+        assert_eq!(context.profile().totals().total(), 0);
+    }
+
+    #[test]
+    fn snapshots_restore_the_whole_machine() {
+        // DEC, ST /3, HALT
+        let mut context = context_with(&[6 << 9, 3, 7 << 9]);
+        context.toggle_breakpoint(Address(u12!(2)));
+        context.run(10);
+        let snapshot = snapshot::Snapshot::from_json(&context.snapshot().to_json()).unwrap();
+        assert_eq!(snapshot, context.snapshot());
+
+        let mut restored = ExecutionContext::default();
+        restored.restore(snapshot);
+        assert_eq!(restored.acc, u12!(4095));
+        assert_eq!(restored.pc, Address(u12!(2)));
+        assert_eq!(restored.instructions_executed(), 2);
+        assert_eq!(restored.last_modifications()[0], Address(u12!(3)));
+        assert_eq!(restored.run(10), StepOutcome::Halted);
+
+        let newer = context.snapshot().to_json().replace(
+            &format!("\"version\": {}", snapshot::SNAPSHOT_VERSION),
+            "\"version\": 1000",
+        );
+        assert!(snapshot::Snapshot::from_json(&newer).is_err());
+        assert!(snapshot::Snapshot::from_json("{}").is_err());
+
+        let past_memory = context
+            .snapshot()
+            .to_json()
+            .replace("\"pc\": 2", "\"pc\": 600");
+        let err = snapshot::Snapshot::from_json(&past_memory).unwrap_err();
+        assert!(err.starts_with("The snapshot is corrupted"), "{}", err);
+    }
 }
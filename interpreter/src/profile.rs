@@ -0,0 +1,88 @@
+//! Counts of how often each word of memory is used, for finding where a program spends its time.
+
+use simplez_common::*;
+use twelve_bit::u12::U12;
+
+/// How often a word of memory was used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// How many times the word was executed as an instruction.
+    pub executions: u64,
+    /// How many times a `LD` or `ADD` read the word.
+    pub reads: u64,
+    /// How many times a `ST` wrote to the word.
+    pub writes: u64,
+}
+
+impl Counts {
+    /// Every use of the word, of any kind.
+    pub fn total(&self) -> u64 {
+        self.executions + self.reads + self.writes
+    }
+}
+
+/// Counts of how the program used each word of memory since the registers were last reset.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// Indexed by address. Addresses past the end have not been used.
+    counts: Vec<Counts>,
+}
+
+impl Profile {
+    /// Counts the execution of `instruction` at `pc` and the word it reads or writes.
+    pub fn record(&mut self, pc: Address, instruction: Instruction) {
+        self.counts_mut(pc).executions += 1;
+        match instruction {
+            Instruction::Load { address } | Instruction::Add { address } => {
+                self.counts_mut(address).reads += 1
+            }
+            Instruction::Store { address } => self.counts_mut(address).writes += 1,
+            _ => (),
+        }
+    }
+
+    pub fn counts(&self, addr: Address) -> Counts {
+        self.counts
+            .get(usize::from(addr.0))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The sum of the counts of every word.
+    pub fn totals(&self) -> Counts {
+        self.counts
+            .iter()
+            .fold(Counts::default(), |totals, counts| Counts {
+                executions: totals.executions + counts.executions,
+                reads: totals.reads + counts.reads,
+                writes: totals.writes + counts.writes,
+            })
+    }
+
+    /// Returns the counts of every word that was used, most used first and by address when tied.
+    pub fn hottest(&self) -> Vec<(Address, Counts)> {
+        let mut used: Vec<(Address, Counts)> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, counts)| counts.total() > 0)
+            .map(|(idx, counts)| (Address(U12::from_u16(idx as u16)), *counts))
+            .collect();
+        used.sort_by(|(a, a_counts), (b, b_counts)| {
+            b_counts.total().cmp(&a_counts.total()).then(a.cmp(b))
+        });
+        used
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    fn counts_mut(&mut self, addr: Address) -> &mut Counts {
+        let idx = usize::from(addr.0);
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, Counts::default());
+        }
+        &mut self.counts[idx]
+    }
+}
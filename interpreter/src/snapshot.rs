@@ -0,0 +1,148 @@
+//! Complete copies of the state of an [`ExecutionContext`], to save and restore later or share as
+//! files.
+//!
+//! Snapshot files are JSON objects with a `format` and a `version` field, so that files written by
+//! older versions can still be read once the format changes. Devices aren't emulated, so only the
+//! words of memory they are mapped to are saved, like any other word.
+
+use std::collections::BTreeSet;
+
+use simplez_common::*;
+use twelve_bit::u12::U12;
+
+use crate::{ExecutionContext, MachineState, Traps};
+
+/// The `format` field of every snapshot file.
+pub const SNAPSHOT_FORMAT: &str = "simplez-snapshot";
+/// The version of the snapshot format written by this version of the interpreter.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// The state of an [`ExecutionContext`] at some point in time.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    pub format: String,
+    pub version: u32,
+    pub acc: U12,
+    pub pc: Address,
+    pub ir: U12,
+    pub memory: Memory,
+    pub debug_info: DebugInfo,
+    pub state: MachineState,
+    pub traps: Traps,
+    pub breakpoints: BTreeSet<Address>,
+    /// The latest modified addresses, most recent first.
+    pub last_modifications: Vec<Address>,
+    /// Addresses the program has stored to since its memory was set.
+    pub stored_to: BTreeSet<Address>,
+    pub instructions_executed: u64,
+    /// The breakpoint execution was paused at, so that resuming doesn't stop there again.
+    #[serde(default)]
+    pub paused_at_breakpoint: Option<Address>,
+}
+
+impl Snapshot {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("snapshots are always serializable")
+    }
+
+    /// Reads a snapshot file, refusing those that aren't snapshots or that were written by a newer
+    /// version of the interpreter.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        #[derive(serde::Deserialize)]
+        struct Header {
+            format: String,
+            version: u32,
+        }
+
+        let header: Header = serde_json::from_str(text)
+            .map_err(|err| format!("This is not a Simplez snapshot: {}", err))?;
+        if header.format != SNAPSHOT_FORMAT {
+            return Err(format!(
+                "This is not a Simplez snapshot, but a `{}` file",
+                header.format
+            ));
+        }
+        if header.version > SNAPSHOT_VERSION {
+            return Err(format!(
+                "The snapshot is of version {}, but only versions up to {} are supported",
+                header.version, SNAPSHOT_VERSION
+            ));
+        }
+        let snapshot: Self = serde_json::from_str(text)
+            .map_err(|err| format!("The snapshot is corrupted: {}", err))?;
+        snapshot
+            .check_addresses()
+            .map_err(|err| format!("The snapshot is corrupted: {}", err))?;
+        Ok(snapshot)
+    }
+
+    /// Makes sure every address in the snapshot is in memory, since the machine would panic on
+    /// reaching one that isn't.
+    fn check_addresses(&self) -> Result<(), String> {
+        let words = self.memory.0.len();
+        let assertions =
+            self.debug_info
+                .assertions()
+                .iter()
+                .filter_map(|assertion| match assertion.target {
+                    AssertionTarget::Word(addr) => Some(addr),
+                    AssertionTarget::Acc => None,
+                });
+        let mut addresses = std::iter::once(self.pc)
+            .chain(self.paused_at_breakpoint)
+            .chain(self.breakpoints.iter().copied())
+            .chain(self.last_modifications.iter().copied())
+            .chain(self.stored_to.iter().copied())
+            .chain(
+                self.debug_info
+                    .symbols()
+                    .iter()
+                    .map(|symbol| symbol.address),
+            )
+            .chain(assertions);
+        match addresses.find(|addr| usize::from(u16::from(addr.0)) >= words) {
+            Some(addr) => Err(format!("address {} is past the end of memory", addr)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ExecutionContext {
+    /// Copies the whole state of the machine, except for its tracer and profile.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            format: SNAPSHOT_FORMAT.to_string(),
+            version: SNAPSHOT_VERSION,
+            acc: self.acc,
+            pc: self.pc,
+            ir: self.ir,
+            memory: self.memory.clone(),
+            debug_info: self.debug_info.clone(),
+            state: self.state,
+            traps: self.traps,
+            breakpoints: self.breakpoints.clone(),
+            last_modifications: self.last_modifications.iter().copied().collect(),
+            stored_to: self.stored_to.clone(),
+            instructions_executed: self.instructions_executed,
+            paused_at_breakpoint: self.paused_at_breakpoint,
+        }
+    }
+
+    /// Puts the machine back in the state it was when `snapshot` was taken. The profile is
+    /// cleared, since it doesn't describe the restored execution.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.acc = snapshot.acc;
+        self.pc = snapshot.pc;
+        self.ir = snapshot.ir;
+        self.memory = snapshot.memory;
+        self.debug_info = snapshot.debug_info;
+        self.state = snapshot.state;
+        self.traps = snapshot.traps;
+        self.breakpoints = snapshot.breakpoints;
+        self.last_modifications = snapshot.last_modifications.into_iter().collect();
+        self.stored_to = snapshot.stored_to;
+        self.instructions_executed = snapshot.instructions_executed;
+        self.paused_at_breakpoint = snapshot.paused_at_breakpoint;
+        self.profile.clear();
+    }
+}
@@ -0,0 +1,164 @@
+//! A record of the instructions an [`ExecutionContext`](crate::ExecutionContext) executes, for
+//! seeing what a program did after the fact.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use simplez_common::*;
+use twelve_bit::u12::U12;
+
+/// How many steps a [`Tracer`] remembers by default.
+pub const DEFAULT_TRACE_LIMIT: usize = 10_000;
+
+/// A word stored by a `ST` instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryWrite {
+    pub address: Address,
+    pub before: U12,
+    pub after: U12,
+}
+
+/// What happened in a single step of execution.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceEntry {
+    /// The number of the step, starting at 1 when the registers are reset.
+    pub step: u64,
+    pub pc: Address,
+    pub ir: U12,
+    pub instruction: Instruction,
+    pub acc_before: U12,
+    pub acc_after: U12,
+    pub write: Option<MemoryWrite>,
+}
+
+impl TraceEntry {
+    /// Whether the entry is one of those `filter` asks for: an address such as `/12`, matching
+    /// steps that execute or write to it, or a mnemonic such as `ld`. An empty filter matches
+    /// everything.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim();
+        if filter.is_empty() {
+            return true;
+        }
+        if filter.starts_with('/') {
+            let address = |addr: Address| addr.to_string() == filter;
+            return address(self.pc) || self.write.iter().any(|write| address(write.address));
+        }
+        let instruction = self.instruction.to_string();
+        let mnemonic = instruction.split(' ').next().unwrap_or_default();
+        mnemonic.eq_ignore_ascii_case(filter)
+    }
+}
+
+/// Records the steps executed by an [`ExecutionContext`](crate::ExecutionContext), keeping only
+/// the most recent ones once it is full.
+#[derive(Clone, Debug)]
+pub struct Tracer {
+    entries: VecDeque<TraceEntry>,
+    limit: usize,
+    /// How many entries were forgotten to make room for newer ones.
+    dropped: u64,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_LIMIT)
+    }
+}
+
+impl Tracer {
+    /// Creates a tracer that remembers up to `limit` steps.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            limit,
+            dropped: 0,
+        }
+    }
+
+    pub fn record(&mut self, entry: TraceEntry) {
+        if self.entries.len() >= self.limit {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Returns the recorded steps, oldest first.
+    pub fn entries(&self) -> &VecDeque<TraceEntry> {
+        &self.entries
+    }
+
+    /// How many steps were forgotten because the tracer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    /// Writes the recorded steps as CSV with a header row. Steps that don't write to memory
+    /// leave the last three columns empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "step,pc,ir,instruction,acc_before,acc_after,write_address,write_before,write_after\n",
+        );
+        for entry in &self.entries {
+            let _ = write!(
+                csv,
+                "{},{},{},{},{},{},",
+                entry.step,
+                u16::from(entry.pc.0),
+                u16::from(entry.ir),
+                entry.instruction,
+                u16::from(entry.acc_before),
+                u16::from(entry.acc_after)
+            );
+            if let Some(write) = entry.write {
+                let _ = write!(
+                    csv,
+                    "{},{},{}",
+                    u16::from(write.address.0),
+                    u16::from(write.before),
+                    u16::from(write.after)
+                );
+            } else {
+                csv.push_str(",,");
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Writes the recorded steps as JSON Lines, one object per step.
+    pub fn to_json_lines(&self) -> String {
+        let mut json = String::new();
+        for entry in &self.entries {
+            let _ = write!(
+                json,
+                "{{\"step\":{},\"pc\":{},\"ir\":{},\"instruction\":\"{}\",\"acc_before\":{},\"acc_after\":{},\"write\":",
+                entry.step,
+                u16::from(entry.pc.0),
+                u16::from(entry.ir),
+                entry.instruction,
+                u16::from(entry.acc_before),
+                u16::from(entry.acc_after)
+            );
+            if let Some(write) = entry.write {
+                let _ = write!(
+                    json,
+                    "{{\"address\":{},\"before\":{},\"after\":{}}}",
+                    u16::from(write.address.0),
+                    u16::from(write.before),
+                    u16::from(write.after)
+                );
+            } else {
+                json.push_str("null");
+            }
+            json.push_str("}\n");
+        }
+        json
+    }
+}
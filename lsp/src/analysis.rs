@@ -0,0 +1,373 @@
+//! Answers the language server's requests about a single document.
+
+use std::ops::Range;
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic, DiagnosticSeverity,
+    DocumentSymbol, Hover, HoverContents, MarkupContent, MarkupKind, Position, SemanticToken,
+    SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend, SymbolKind, TextEdit,
+};
+use simplez_assembler::completion::{self, CompletionKind};
+use simplez_assembler::keywords;
+use simplez_assembler::tokens::{self, Token, TokenKind};
+use simplez_common::{DebugInfo, Instruction, Memory};
+
+use crate::line_index::LineIndex;
+
+/// The token types of [`Analysis::semantic_tokens`], indexed by their `token_type`.
+const TOKEN_TYPES: [SemanticTokenType; 5] = [
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+];
+
+pub fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: vec![SemanticTokenModifier::DECLARATION],
+    }
+}
+
+/// A document along with everything the assembler knows about it.
+pub struct Analysis<'s> {
+    source: &'s str,
+    index: LineIndex<'s>,
+    tokens: Vec<Token>,
+    assembled: Result<(Memory, DebugInfo), simplez_assembler::Error<&'s str>>,
+}
+
+impl<'s> Analysis<'s> {
+    pub fn new(source: &'s str) -> Self {
+        Self {
+            source,
+            index: LineIndex::new(source),
+            tokens: tokens::tokenize(source),
+            assembled: simplez_assembler::assemble_with_debug_info(source),
+        }
+    }
+
+    /// Reports the error that stops the program from assembling, if any.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match &self.assembled {
+            Ok(_) => Vec::new(),
+            Err(err) => vec![Diagnostic {
+                range: self.index.range(err.span(self.source)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("simplez".to_string()),
+                message: err.kind.to_string(),
+                ..Default::default()
+            }],
+        }
+    }
+
+    pub fn hover(&self, position: Position) -> Option<Hover> {
+        let token = self.token_at(position)?;
+        let contents = match token.kind {
+            TokenKind::LabelDefinition | TokenKind::LabelReference { .. } => {
+                self.label_help(token.label_name(self.source)?)
+            }
+            TokenKind::Mnemonic | TokenKind::Directive => {
+                let keyword = keywords::keyword(&self.source[token.span.clone()])?;
+                let mut help = format!("```simplez\n{}\n```\n{}", keyword.syntax, keyword.summary);
+                if let Some(encoding) = keyword.encoding() {
+                    help += &format!("\n\nEncoding: `{}`", encoding);
+                }
+                help
+            }
+            _ => return None,
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: contents,
+            }),
+            range: Some(self.index.range(token.span.clone())),
+        })
+    }
+
+    fn label_help(&self, name: &str) -> String {
+        let symbol = match &self.assembled {
+            Ok((memory, debug_info)) => debug_info
+                .symbol(name)
+                .map(|symbol| (symbol.address, memory.0.get(usize::from(symbol.address.0)))),
+            Err(_) => None,
+        };
+        match symbol {
+            Some((address, Some(word))) => format!(
+                "`{}`: address `{}`, assembled to `{:#06o}` (`{}`)",
+                name,
+                address,
+                u16::from(*word),
+                Instruction::from(*word)
+            ),
+            Some((address, None)) => format!("`{}`: address `{}`", name, address),
+            None if self.definition_span(name).is_none() => format!("Undefined label `{}`", name),
+            None => format!(
+                "`{}`: fix the errors in the program to see its address",
+                name
+            ),
+        }
+    }
+
+    /// Returns where the label at `position` is defined.
+    pub fn definition(&self, position: Position) -> Option<lsp_types::Range> {
+        let name = self.token_at(position)?.label_name(self.source)?;
+        Some(self.index.range(self.definition_span(name)?))
+    }
+
+    fn definition_span(&self, name: &str) -> Option<Range<usize>> {
+        self.label_spans(name)
+            .find(|(_, is_definition)| *is_definition)
+            .map(|(span, _)| span)
+    }
+
+    /// Returns everywhere the label at `position` is used.
+    pub fn references(
+        &self,
+        position: Position,
+        include_definition: bool,
+    ) -> Vec<lsp_types::Range> {
+        let name = match self
+            .token_at(position)
+            .and_then(|token| token.label_name(self.source))
+        {
+            Some(name) => name,
+            None => return Vec::new(),
+        };
+        self.label_spans(name)
+            .filter(|(_, is_definition)| include_definition || !is_definition)
+            .map(|(span, _)| self.index.range(span))
+            .collect()
+    }
+
+    /// Renames the label at `position` everywhere it is used.
+    pub fn rename(&self, position: Position, new_name: &str) -> Result<Vec<TextEdit>, String> {
+        let name = self
+            .token_at(position)
+            .and_then(|token| token.label_name(self.source))
+            .ok_or("Only labels can be renamed")?;
+        if !tokens::is_label(new_name) {
+            return Err(format!("`{}` is not a valid label name", new_name));
+        }
+        if new_name != name && self.definition_span(new_name).is_some() {
+            return Err(format!("Label `{}` is already defined", new_name));
+        }
+        Ok(self
+            .label_spans(name)
+            .map(|(span, _)| TextEdit::new(self.index.range(span), new_name.to_string()))
+            .collect())
+    }
+
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let completions = completion::complete(self.source, self.index.offset(position));
+        let range = self.index.range(completions.span);
+        completions
+            .items
+            .into_iter()
+            .map(|item| CompletionItem {
+                label: item.text.clone(),
+                kind: Some(match item.kind {
+                    CompletionKind::Mnemonic | CompletionKind::Directive => {
+                        CompletionItemKind::KEYWORD
+                    }
+                    CompletionKind::Label => CompletionItemKind::CONSTANT,
+                }),
+                detail: Some(item.detail),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, item.text))),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Lists the labels of the program. Labels of `DATA` and `RES` statements are variables,
+    /// and the rest are code.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        self.tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.kind == TokenKind::LabelDefinition)
+            .map(|(idx, token)| {
+                let name = &self.source[token.span.clone()];
+                let command = self
+                    .tokens
+                    .get(idx + 1)
+                    .filter(|command| self.line_of(command) == self.line_of(token))
+                    .map(|command| self.source[command.span.clone()].to_uppercase());
+                let kind = match command.as_deref() {
+                    Some("DATA") | Some("RES") => SymbolKind::VARIABLE,
+                    _ => SymbolKind::FUNCTION,
+                };
+                let detail = match &self.assembled {
+                    Ok((_, debug_info)) => debug_info
+                        .symbol(name)
+                        .map(|symbol| symbol.address.to_string()),
+                    Err(_) => None,
+                };
+                let line_end = self.source[token.span.start..]
+                    .find('\n')
+                    .map_or(self.source.len(), |idx| token.span.start + idx);
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: name.to_string(),
+                    detail,
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range: self.index.range(token.span.start..line_end),
+                    selection_range: self.index.range(token.span.clone()),
+                    children: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Encodes the tokens of the program as LSP semantic tokens, following
+    /// [`semantic_tokens_legend`].
+    pub fn semantic_tokens(&self) -> Vec<SemanticToken> {
+        let mut previous = Position::new(0, 0);
+        let mut semantic_tokens = Vec::new();
+        for token in &self.tokens {
+            let token_type = match token.kind {
+                TokenKind::Mnemonic => SemanticTokenType::KEYWORD,
+                TokenKind::Directive => SemanticTokenType::MACRO,
+                TokenKind::LabelDefinition | TokenKind::LabelReference { .. } => {
+                    SemanticTokenType::VARIABLE
+                }
+                TokenKind::Address | TokenKind::Number => SemanticTokenType::NUMBER,
+                TokenKind::Comment => SemanticTokenType::COMMENT,
+                TokenKind::Error => continue,
+            };
+            let range = self.index.range(token.span.clone());
+            let delta_line = range.start.line - previous.line;
+            semantic_tokens.push(SemanticToken {
+                delta_line,
+                delta_start: if delta_line == 0 {
+                    range.start.character - previous.character
+                } else {
+                    range.start.character
+                },
+                length: range.end.character - range.start.character,
+                token_type: TOKEN_TYPES.iter().position(|t| *t == token_type).unwrap() as u32,
+                token_modifiers_bitset: (token.kind == TokenKind::LabelDefinition) as u32,
+            });
+            previous = range.start;
+        }
+        semantic_tokens
+    }
+
+    /// Returns the token at `position`, or the one that ends right before it, where the cursor
+    /// is after typing a word.
+    fn token_at(&self, position: Position) -> Option<&Token> {
+        let offset = self.index.offset(position);
+        self.tokens
+            .iter()
+            .find(|token| token.span.contains(&offset))
+            .or_else(|| self.tokens.iter().find(|token| token.span.end == offset))
+    }
+
+    /// Returns the spans of the name of label `name` wherever it appears, without the `/` of
+    /// references, along with whether it is being defined there.
+    fn label_spans<'a>(&'a self, name: &'a str) -> impl Iterator<Item = (Range<usize>, bool)> + 'a {
+        self.tokens.iter().filter_map(move |token| {
+            if token.label_name(self.source)? != name {
+                return None;
+            }
+            Some(match token.kind {
+                TokenKind::LabelDefinition => (token.span.clone(), true),
+                _ => (token.span.start + 1..token.span.end, false),
+            })
+        })
+    }
+
+    fn line_of(&self, token: &Token) -> u32 {
+        self.index.position(token.span.start).line
+    }
+}
+
+#[cfg(test)]
+const PROGRAM: &str = "start   LD /x\n        BR /start ; loop\nx       DATA 5\n        LD /y\n";
+
+#[cfg(test)]
+#[test]
+fn navigate_labels() {
+    let analysis = Analysis::new(PROGRAM);
+    let range = |line, start, end| {
+        lsp_types::Range::new(Position::new(line, start), Position::new(line, end))
+    };
+
+    assert_eq!(
+        analysis.definition(Position::new(0, 12)),
+        Some(range(2, 0, 1))
+    );
+    assert_eq!(
+        analysis.definition(Position::new(0, 13)),
+        Some(range(2, 0, 1))
+    );
+    assert_eq!(analysis.definition(Position::new(0, 9)), None);
+    assert_eq!(
+        analysis.references(Position::new(0, 2), false),
+        [range(1, 12, 17)]
+    );
+    assert_eq!(analysis.references(Position::new(1, 14), true).len(), 2);
+
+    let edits = analysis.rename(Position::new(2, 0), "value").unwrap();
+    assert_eq!(
+        edits.iter().map(|edit| edit.range).collect::<Vec<_>>(),
+        [range(0, 12, 13), range(2, 0, 1)]
+    );
+    assert!(analysis.rename(Position::new(2, 0), "start").is_err());
+    assert!(analysis.rename(Position::new(2, 0), "2x").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn diagnostics_and_tokens() {
+    let analysis = Analysis::new(PROGRAM);
+    let diagnostics = analysis.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Undefined label `y`");
+    assert_eq!(
+        diagnostics[0].range,
+        lsp_types::Range::new(Position::new(3, 12), Position::new(3, 13))
+    );
+
+    let tokens: Vec<(u32, u32, u32, u32, u32)> = analysis.semantic_tokens()[..4]
+        .iter()
+        .map(|t| {
+            (
+                t.delta_line,
+                t.delta_start,
+                t.length,
+                t.token_type,
+                t.token_modifiers_bitset,
+            )
+        })
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            (0, 0, 5, 2, 1),
+            (0, 8, 2, 0, 0),
+            (0, 3, 2, 2, 0),
+            (1, 8, 2, 0, 0)
+        ]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn words_past_the_end_of_memory() {
+    let analysis = Analysis::new("        ORG 600\n        HALT\n");
+    let diagnostics = analysis.diagnostics();
+    assert_eq!(diagnostics[0].message, "There is no memory past /511");
+    assert_eq!(diagnostics[0].range.start, Position::new(1, 8));
+}
+
+#[cfg(test)]
+#[test]
+fn addresses_too_large_for_a_word() {
+    let analysis = Analysis::new("        LD /99999\n");
+    assert_eq!(analysis.diagnostics()[0].message, "Invalid number");
+}
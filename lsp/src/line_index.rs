@@ -0,0 +1,65 @@
+use std::ops::Range;
+
+use lsp_types::Position;
+
+/// Converts between byte offsets into a document and LSP positions, whose columns count UTF-16
+/// code units.
+pub struct LineIndex<'s> {
+    source: &'s str,
+    /// Byte offset of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+impl<'s> LineIndex<'s> {
+    pub fn new(source: &'s str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let character = self.source[line_start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    /// Returns the byte offset of `position`, clamped to the end of its line and of the source.
+    pub fn offset(&self, position: Position) -> usize {
+        let line_start = match self.line_starts.get(position.line as usize) {
+            Some(start) => *start,
+            None => return self.source.len(),
+        };
+        let line = self.source[line_start..].split('\n').next().unwrap_or("");
+        let mut units = 0;
+        for (idx, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return line_start + idx;
+            }
+            units += c.len_utf16();
+        }
+        line_start + line.len()
+    }
+
+    pub fn range(&self, span: Range<usize>) -> lsp_types::Range {
+        lsp_types::Range::new(self.position(span.start), self.position(span.end))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn positions_count_utf16() {
+    let source = "a\n; ñ𝄞x\nlast";
+    let index = LineIndex::new(source);
+    let x = source.find('x').unwrap();
+    assert_eq!(index.position(x), Position::new(1, 5));
+    assert_eq!(index.offset(Position::new(1, 5)), x);
+    assert_eq!(index.position(source.len()), Position::new(2, 4));
+    assert_eq!(index.offset(Position::new(0, 10)), 1);
+    assert_eq!(index.offset(Position::new(7, 0)), source.len());
+}
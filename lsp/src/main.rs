@@ -0,0 +1,234 @@
+//! A language server for Simplez assembly, talking the Language Server Protocol over stdio.
+
+mod analysis;
+mod line_index;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Rename,
+    Request as LspRequest, SemanticTokensFullRequest,
+};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, DocumentSymbolResponse, GotoDefinitionResponse,
+    HoverProviderCapability, Location, OneOf, PublishDiagnosticsParams, SemanticTokens,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url, WorkspaceEdit,
+};
+
+use analysis::Analysis;
+
+fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                connection
+                    .sender
+                    .send(Message::Response(server.handle_request(request)))?;
+            }
+            Message::Notification(notification) => {
+                for notification in server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => (),
+        }
+    }
+
+    // The writer thread only stops once the connection is dropped.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["/".to_string()]),
+            ..Default::default()
+        }),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: analysis::semantic_tokens_legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+/// The documents open in the client, by URI.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, |server, params| {
+                let position = params.text_document_position_params;
+                let source = server.document(&position.text_document.uri)?;
+                Ok(Analysis::new(source).hover(position.position))
+            }),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, |server, params| {
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let source = server.document(&uri)?;
+                Ok(Analysis::new(source)
+                    .definition(position.position)
+                    .map(|range| GotoDefinitionResponse::Scalar(Location::new(uri, range))))
+            }),
+            References::METHOD => self.respond::<References>(request, |server, params| {
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                let source = server.document(&uri)?;
+                let ranges = Analysis::new(source)
+                    .references(position.position, params.context.include_declaration);
+                Ok(Some(
+                    ranges
+                        .into_iter()
+                        .map(|range| Location::new(uri.clone(), range))
+                        .collect(),
+                ))
+            }),
+            Rename::METHOD => self.respond::<Rename>(request, |server, params| {
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                let source = server.document(&uri)?;
+                let edits = Analysis::new(source).rename(position.position, &params.new_name)?;
+                Ok(Some(WorkspaceEdit::new(HashMap::from([(uri, edits)]))))
+            }),
+            Completion::METHOD => self.respond::<Completion>(request, |server, params| {
+                let position = params.text_document_position;
+                let source = server.document(&position.text_document.uri)?;
+                let items = Analysis::new(source).completion(position.position);
+                Ok(Some(CompletionResponse::Array(items)))
+            }),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, |server, params| {
+                    let source = server.document(&params.text_document.uri)?;
+                    let symbols = Analysis::new(source).document_symbols();
+                    Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+                })
+            }
+            SemanticTokensFullRequest::METHOD => {
+                self.respond::<SemanticTokensFullRequest>(request, |server, params| {
+                    let source = server.document(&params.text_document.uri)?;
+                    Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data: Analysis::new(source).semantic_tokens(),
+                    })))
+                })
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request {}", request.method),
+            ),
+        }
+    }
+
+    /// Answers `request` with `handler`, reporting the errors it returns to the client.
+    fn respond<R: LspRequest>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Self, R::Params) -> Result<R::Result, String>,
+    ) -> Response {
+        let id: RequestId = request.id.clone();
+        match request.extract::<R::Params>(R::METHOD) {
+            Ok((id, params)) => match handler(self, params) {
+                Ok(result) => Response::new_ok(id, result),
+                Err(message) => Response::new_err(id, ErrorCode::InvalidParams as i32, message),
+            },
+            Err(err) => Response::new_err(id, ErrorCode::InvalidParams as i32, err.to_string()),
+        }
+    }
+
+    /// Keeps track of the open documents, returning the diagnostics to publish for them.
+    fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                match notification
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+                {
+                    Ok(params) => {
+                        let document = params.text_document;
+                        self.documents.insert(document.uri.clone(), document.text);
+                        document.uri
+                    }
+                    Err(_) => return Vec::new(),
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                match notification.extract::<lsp_types::DidChangeTextDocumentParams>(
+                    DidChangeTextDocument::METHOD,
+                ) {
+                    // Documents are synced in full, so the last change holds all the text.
+                    Ok(mut params) => match params.content_changes.pop() {
+                        Some(change) => {
+                            let uri = params.text_document.uri;
+                            self.documents.insert(uri.clone(), change.text);
+                            uri
+                        }
+                        None => return Vec::new(),
+                    },
+                    Err(_) => return Vec::new(),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                match notification
+                    .extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+                {
+                    Ok(params) => {
+                        let uri = params.text_document.uri;
+                        self.documents.remove(&uri);
+                        return vec![publish_diagnostics(uri, Vec::new())];
+                    }
+                    Err(_) => return Vec::new(),
+                }
+            }
+            _ => return Vec::new(),
+        };
+
+        let diagnostics = Analysis::new(&self.documents[&uri]).diagnostics();
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    fn document(&self, uri: &Url) -> Result<&str, String> {
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .ok_or_else(|| format!("{} is not open", uri))
+    }
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams::new(uri, diagnostics, None),
+    )
+}
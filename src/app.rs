@@ -3,39 +3,177 @@ use eframe::{
     epaint::vec2,
 };
 use simplez_common::{Address, Instruction};
+use simplez_interpreter::snapshot::Snapshot;
+use simplez_interpreter::MachineState;
 use twelve_bit::u12::U12;
 
+use crate::autocomplete::Autocomplete;
+use crate::code_info;
+use crate::control_flow;
+use crate::document::{self, Document};
+use crate::examples;
+use crate::file;
 use crate::highlighter;
+use crate::memory_diff;
+use crate::profile;
+use crate::settings::Settings;
+use crate::snapshots;
+use crate::trace;
+use crate::word;
 
+/// How many paths File > Open Recent remembers.
+const MAX_RECENT_FILES: usize = 10;
+
+/// How each column of the registers and memory tables is displayed.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct DisplayFormats {
+    acc: word::WordFormat,
+    pc: word::WordFormat,
+    ir: word::WordFormat,
+    address: word::WordFormat,
+    contents: word::WordFormat,
+}
+
+impl Default for DisplayFormats {
+    fn default() -> Self {
+        Self {
+            acc: word::WordFormat::Decimal,
+            pc: word::WordFormat::Decimal,
+            ir: word::WordFormat::Decimal,
+            address: word::WordFormat::Decimal,
+            contents: word::WordFormat::Binary,
+        }
+    }
+}
+
+/// How quickly the Run button executes the program.
 #[derive(serde::Deserialize, serde::Serialize)]
-struct AssemblerError {
-    loc: usize,
-    description: String,
+#[serde(default)]
+struct RunSpeed {
+    /// Ignore `instructions_per_second` and execute `instructions_per_frame` every frame instead.
+    unlimited: bool,
+    instructions_per_second: f32,
+    instructions_per_frame: usize,
+    /// Fraction of an instruction carried over from the last frame.
+    #[serde(skip)]
+    pending: f32,
+}
+
+impl Default for RunSpeed {
+    fn default() -> Self {
+        Self {
+            unlimited: false,
+            instructions_per_second: 60.,
+            instructions_per_frame: 1000,
+            pending: 0.,
+        }
+    }
+}
+
+impl RunSpeed {
+    /// Returns how many instructions to execute in a frame that took `dt` seconds.
+    fn steps_for_frame(&mut self, dt: f32) -> usize {
+        if self.unlimited {
+            return self.instructions_per_frame;
+        }
+        self.pending += self.instructions_per_second * dt;
+        let steps = self.pending.floor();
+        self.pending -= steps;
+        steps as usize
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Speed:");
+            ui.add_enabled(
+                !self.unlimited,
+                egui::Slider::new(&mut self.instructions_per_second, 1. ..=10_000.)
+                    .logarithmic(true)
+                    .suffix(" instr/s"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.unlimited, "As fast as possible:");
+            ui.add_enabled(
+                self.unlimited,
+                egui::DragValue::new(&mut self.instructions_per_frame)
+                    .clamp_range(1..=document::RUN_TO_HALT_STEPS_PER_FRAME)
+                    .suffix(" instr/frame"),
+            );
+        });
+    }
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct App {
-    program: String,
-    assembler_err: Option<AssemblerError>,
-    context: simplez_interpreter::ExecutionContext,
+    /// The programs open in the editor tabs. There is always at least one.
+    documents: Vec<Document>,
+    /// Index into `documents` of the tab being shown.
+    active: usize,
+    /// Most recently used first.
+    recent_files: Vec<String>,
+    run_speed: RunSpeed,
+    formats: DisplayFormats,
+    /// Whether reassembling while paused keeps the current values of `DATA` and `RES` words.
+    keep_data: bool,
+    settings: Settings,
 
     #[serde(skip)]
-    executing: bool,
+    show_settings: bool,
+    #[serde(skip)]
+    show_control_flow: bool,
+    #[serde(skip)]
+    show_trace: bool,
     #[serde(skip)]
-    ran_program: bool,
+    show_profile: bool,
+    #[serde(skip)]
+    show_snapshots: bool,
+    #[serde(skip)]
+    show_memory_diff: bool,
+    #[serde(skip)]
+    snapshots_view: snapshots::SnapshotsView,
+    #[serde(skip)]
+    profile_view: profile::ProfileView,
+    #[serde(skip)]
+    memory_diff_view: memory_diff::MemoryDiffView,
+    #[serde(skip)]
+    trace_view: trace::TraceView,
+    #[serde(skip)]
+    file_dialogs: file::FileDialogs,
+    #[serde(skip)]
+    file_error: Option<String>,
+    /// A tab with unsaved changes waiting for the user to confirm that it can be closed.
+    #[serde(skip)]
+    pending_close: Option<usize>,
 }
 
 impl Default for App {
     fn default() -> Self {
         Self {
-            program: String::new(),
-            assembler_err: None,
-            context: Default::default(),
+            documents: vec![Document::default()],
+            active: 0,
+            recent_files: Vec::new(),
+            run_speed: Default::default(),
+            formats: Default::default(),
+            keep_data: false,
+            settings: Default::default(),
 
-            executing: false,
-            ran_program: false,
+            show_settings: false,
+            show_control_flow: false,
+            show_trace: false,
+            show_profile: false,
+            show_snapshots: false,
+            show_memory_diff: false,
+            snapshots_view: Default::default(),
+            profile_view: Default::default(),
+            memory_diff_view: Default::default(),
+            trace_view: Default::default(),
+            file_dialogs: Default::default(),
+            file_error: None,
+            pending_close: None,
         }
     }
 }
@@ -48,11 +186,29 @@ impl App {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        let mut app: Self = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+        if app.documents.is_empty() {
+            app.documents.push(Document::default());
         }
+        app.active = app.active.min(app.documents.len() - 1);
+        #[cfg(target_arch = "wasm32")]
+        match crate::share::take_shared_program() {
+            Some(Ok(program)) => {
+                let mut document = Document::new(None, program);
+                document.name = Some("shared.sz".to_owned());
+                app.add_document(document);
+            }
+            Some(Err(err)) => {
+                app.file_error = Some(format!("Could not open the shared program: {}", err))
+            }
+            None => (),
+        }
+        cc.egui_ctx.set_visuals(app.settings.visuals());
 
-        Default::default()
+        app
     }
 }
 
@@ -65,6 +221,178 @@ impl eframe::App for App {
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        match self.file_dialogs.take_opened() {
+            Some(Ok(file)) => self.open_document(file.path, file.contents),
+            Some(Err(err)) => self.file_error = Some(err),
+            None => (),
+        }
+        match self.file_dialogs.take_opened_snapshot() {
+            Some(Ok(file)) => match Snapshot::from_json(&file.contents) {
+                Ok(snapshot) => {
+                    let name = file.path.rsplit(['/', '\\']).next().unwrap_or(&file.path);
+                    let name = name.strip_suffix(".json").unwrap_or(name).to_owned();
+                    self.documents[self.active].add_snapshot(name, snapshot);
+                    self.show_snapshots = true;
+                }
+                Err(err) => {
+                    self.file_error = Some(format!("Could not import {}: {}", file.path, err))
+                }
+            },
+            Some(Err(err)) => self.file_error = Some(err),
+            None => (),
+        }
+
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| self.file_menu_ui(ui));
+                ui.menu_button("Examples", |ui| self.examples_menu_ui(ui));
+                if ui.button("Settings").clicked() {
+                    self.show_settings = true;
+                }
+                ui.toggle_value(&mut self.show_control_flow, "Control flow")
+                    .on_hover_text("Show the graph of the program's basic blocks");
+                ui.toggle_value(&mut self.show_trace, "Trace")
+                    .on_hover_text("Record the instructions executed and what they did");
+                ui.toggle_value(&mut self.show_profile, "Profile")
+                    .on_hover_text("Show the words the program used most");
+                ui.toggle_value(&mut self.show_snapshots, "Snapshots")
+                    .on_hover_text("Save the machine and go back to it later");
+                ui.toggle_value(&mut self.show_memory_diff, "Memory diff")
+                    .on_hover_text("Compare the memory with a baseline pinned earlier");
+                let doc = &mut self.documents[self.active];
+                if ui
+                    .add_enabled(doc.is_editable(), egui::Button::new("Format"))
+                    .on_hover_text("Align the columns of the program and normalize its mnemonics")
+                    .clicked()
+                    && doc.format(self.settings.format_options())
+                {
+                    if doc.ran_program {
+                        doc.hot_reassemble(self.keep_data);
+                    } else {
+                        doc.assemble_program();
+                    }
+                }
+                ui.label(doc.title());
+            });
+        });
+
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (index, doc) in self.documents.iter().enumerate() {
+                    let mut tab = ui.selectable_value(&mut self.active, index, doc.title());
+                    if let Some(path) = &doc.file_path {
+                        tab = tab.on_hover_text(path);
+                    }
+                    let close = ui.small_button("x").on_hover_text("Close tab").clicked();
+                    if tab.middle_clicked() || close {
+                        self.pending_close = Some(index);
+                    }
+                    ui.separator();
+                }
+                if ui.small_button("+").on_hover_text("New tab").clicked() {
+                    self.new_document();
+                }
+            });
+        });
+
+        if let Some(index) = self.pending_close {
+            if self.documents[index].unsaved_changes {
+                let mut confirmed = None;
+                egui::Window::new("Unsaved changes")
+                    .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "Close {} and discard its unsaved changes?",
+                            self.documents[index].title()
+                        ));
+                        ui.horizontal(|ui| {
+                            if ui.button("Discard changes").clicked() {
+                                confirmed = Some(true);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                confirmed = Some(false);
+                            }
+                        });
+                    });
+                match confirmed {
+                    Some(true) => self.close_document(index),
+                    Some(false) => self.pending_close = None,
+                    None => (),
+                }
+            } else {
+                self.close_document(index);
+            }
+        }
+
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .resizable(false)
+            .show(ctx, |ui| self.settings.ui(ui));
+
+        egui::Window::new("Control flow")
+            .open(&mut self.show_control_flow)
+            .default_size([320., 480.])
+            .show(ctx, |ui| {
+                egui::ScrollArea::both().show(ui, |ui| {
+                    control_flow::graph_ui(ui, &self.documents[self.active].context)
+                });
+            });
+
+        egui::Window::new("Trace")
+            .open(&mut self.show_trace)
+            .default_size([420., 360.])
+            .show(ctx, |ui| {
+                let context = &mut self.documents[self.active].context;
+                if let Err(err) = self.trace_view.ui(ui, context, self.formats.acc) {
+                    self.file_error = Some(err);
+                }
+            });
+
+        egui::Window::new("Snapshots")
+            .open(&mut self.show_snapshots)
+            .show(ctx, |ui| {
+                let doc = &mut self.documents[self.active];
+                if let Err(err) = self.snapshots_view.ui(ui, doc, &self.file_dialogs) {
+                    self.file_error = Some(err);
+                }
+            });
+
+        egui::Window::new("Memory diff")
+            .open(&mut self.show_memory_diff)
+            .default_size([520., 360.])
+            .show(ctx, |ui| {
+                self.memory_diff_view.ui(
+                    ui,
+                    &mut self.documents[self.active],
+                    self.formats.address,
+                    self.formats.contents,
+                )
+            });
+
+        egui::Window::new("Profile")
+            .open(&mut self.show_profile)
+            .default_size([420., 360.])
+            .show(ctx, |ui| {
+                self.profile_view
+                    .ui(ui, &self.documents[self.active].context)
+            });
+
+        if let Some(err) = &self.file_error {
+            let mut open = true;
+            egui::Window::new("Error")
+                .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| ui.label(err));
+            if !open {
+                self.file_error = None;
+            }
+        }
+
+        let doc = &mut self.documents[self.active];
         egui::SidePanel::left("state_panel")
             .resizable(true)
             .default_width(400.)
@@ -80,95 +408,220 @@ impl eframe::App for App {
                         .columns(egui_extras::Size::relative(1. / 3.), 3)
                         .header(heading_height, |mut header| {
                             header.col(|ui| {
-                                ui.heading("ACC (Z)");
+                                self.formats.acc.header_ui(ui, "ACC (Z)");
                             });
                             header.col(|ui| {
-                                ui.heading("PC");
+                                self.formats.pc.header_ui_among(
+                                    ui,
+                                    "PC",
+                                    &word::WordFormat::ADDRESS,
+                                );
                             });
                             header.col(|ui| {
-                                ui.heading("IR");
+                                self.formats.ir.header_ui(ui, "IR");
                             });
                         })
                         .body(|mut body| {
                             body.row(16., |mut row| {
                                 row.col(|ui| {
-                                    ui.monospace(format!(
+                                    let format = self.formats.acc;
+                                    let label = egui::RichText::new(format!(
                                         "{} ({})",
-                                        u16::from(self.context.acc),
-                                        self.context.zero() as u8
-                                    ));
+                                        format.format(doc.context.acc),
+                                        doc.context.zero() as u8
+                                    ))
+                                    .monospace();
+                                    if let Some(value) = word::editable_word(
+                                        ui,
+                                        &mut doc.word_editor,
+                                        egui::Id::new("acc"),
+                                        0o7777,
+                                        || format.format_editable(doc.context.acc),
+                                        label,
+                                    ) {
+                                        doc.context.acc = value;
+                                    }
                                 });
                                 row.col(|ui| {
-                                    ui.monospace(format!("[{}]", u16::from(self.context.pc.0)));
+                                    let format = self.formats.pc;
+                                    let label = egui::RichText::new(format!(
+                                        "[{}]",
+                                        format.format(doc.context.pc.0)
+                                    ))
+                                    .monospace();
+                                    if let Some(value) = word::editable_word(
+                                        ui,
+                                        &mut doc.word_editor,
+                                        egui::Id::new("pc"),
+                                        0o777,
+                                        || format.format_editable(doc.context.pc.0),
+                                        label,
+                                    ) {
+                                        doc.context.pc = Address(value);
+                                    }
                                 });
                                 row.col(|ui| {
-                                    ui.monospace(format!(
+                                    let label = egui::RichText::new(format!(
                                         "{} ({})",
-                                        u16::from(self.context.ir),
-                                        Instruction::from(self.context.ir)
-                                    ));
+                                        self.formats.ir.format(doc.context.ir),
+                                        Instruction::from(doc.context.ir)
+                                    ))
+                                    .monospace();
+                                    if let Some(value) = word::editable_word(
+                                        ui,
+                                        &mut doc.word_editor,
+                                        egui::Id::new("ir"),
+                                        0o7777,
+                                        || Instruction::from(doc.context.ir).to_string(),
+                                        label,
+                                    ) {
+                                        doc.context.ir = value;
+                                    }
                                 });
                             });
                         });
                 });
 
                 ui.vertical_centered(|ui| ui.heading("Execution"));
+                let running = doc.context.state() == MachineState::Running;
                 ui.horizontal(|ui| {
                     if ui
-                        .button(if self.executing { "Pause" } else { "Run" })
+                        .add_enabled(
+                            running,
+                            egui::Button::new(if doc.executing { "Pause" } else { "Run" }),
+                        )
                         .clicked()
                     {
-                        self.executing = !self.executing;
+                        doc.executing = !doc.executing;
+                        doc.running_to_halt = false;
+                        self.run_speed.pending = 0.;
+                        doc.last_outcome = None;
+                    }
+                    if ui
+                        .add_enabled(running && !doc.executing, egui::Button::new("Run to halt"))
+                        .clicked()
+                    {
+                        doc.executing = true;
+                        doc.running_to_halt = true;
+                        doc.last_outcome = None;
                     }
                     if ui.button("Reset").clicked() {
-                        self.context.reset_registers();
+                        doc.context.reset_registers();
+                        doc.last_outcome = None;
                     }
                     if ui
-                        .add_enabled(!self.executing, egui::Button::new("Step"))
+                        .add_enabled(running && !doc.executing, egui::Button::new("Step"))
                         .clicked()
                     {
-                        self.ran_program = true;
-                        self.context.step();
+                        doc.step();
                     }
                 });
+                self.run_speed.ui(ui);
+                ui.horizontal(|ui| {
+                    ui.label("State:");
+                    ui.monospace(doc.context.state().to_string());
+                    ui.label("Instructions executed:");
+                    ui.monospace(doc.context.instructions_executed().to_string());
+                });
+                if let Some(outcome) = doc.last_outcome {
+                    ui.colored_label(ui.visuals().warn_fg_color, outcome.to_string());
+                }
+                if doc.context.traps.failed_assertion
+                    && doc.context.state() != MachineState::Running
+                {
+                    for failed in doc.context.failed_assertions() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!(
+                                "Expected {} = {}, got {}",
+                                failed.assertion.name,
+                                u16::from(failed.assertion.expected),
+                                u16::from(failed.actual)
+                            ),
+                        );
+                    }
+                }
+                ui.collapsing("Stop execution when...", |ui| {
+                    let traps = &mut doc.context.traps;
+                    ui.checkbox(&mut traps.executed_data_word, "A DATA word is executed");
+                    ui.checkbox(&mut traps.pc_wrapped_around, "PC wraps around to /0");
+                    ui.checkbox(&mut traps.wrote_to_code, "An instruction is overwritten");
+                    ui.checkbox(&mut traps.failed_assertion, "An ;@expect assertion fails")
+                        .on_hover_text("Check the program's ;@expect comments when it halts");
+                });
 
                 ui.vertical_centered(|ui| ui.heading("Memory"));
                 let text_color = ui.style().visuals.text_color();
                 let mut loc_rect = ui.available_rect_before_wrap();
                 let mut render_loc_rect = false;
-                egui_extras::TableBuilder::new(ui)
+                let memory_view = &self.settings.memory_view;
+                let addresses: Vec<Address> = (0..doc.context.memory().0.len())
+                    .map(|addr| Address(U12::from_u16(addr as u16)))
+                    .filter(|addr| {
+                        !memory_view.only_assembled_words
+                            || *addr == doc.context.pc
+                            || doc.context.debug_info().word_kind(*addr).is_some()
+                            || doc.context.memory()[*addr] != U12::from_u16(0)
+                    })
+                    .collect();
+                let hottest = doc
+                    .context
+                    .profile()
+                    .hottest()
+                    .first()
+                    .map_or(0, |(_, counts)| counts.total());
+                let mut table = egui_extras::TableBuilder::new(ui)
                     .striped(true)
                     .cell_layout(egui::Layout::centered_and_justified(
                         egui::Direction::LeftToRight,
                     ))
-                    .column(egui_extras::Size::relative(1. / 3.))
-                    .column(egui_extras::Size::remainder())
-                    .column(egui_extras::Size::relative(1. / 3.))
+                    .column(egui_extras::Size::relative(1. / 3.));
+                if memory_view.show_heat_map {
+                    table = table.column(egui_extras::Size::exact(50.));
+                }
+                table = table.column(egui_extras::Size::remainder());
+                if memory_view.show_instructions {
+                    table = table.column(egui_extras::Size::relative(1. / 3.));
+                }
+                table
                     .header(heading_height, |mut header| {
                         header.col(|ui| {
-                            ui.heading("Address (DEC)");
-                        });
-                        header.col(|ui| {
-                            ui.heading("Contents (BIN)");
+                            self.formats.address.header_ui_among(
+                                ui,
+                                "Address",
+                                &word::WordFormat::ADDRESS,
+                            );
                         });
+                        if memory_view.show_heat_map {
+                            header.col(|ui| {
+                                ui.heading("Uses");
+                            });
+                        }
                         header.col(|ui| {
-                            ui.heading("Instruction");
+                            self.formats.contents.header_ui(ui, "Contents");
                         });
+                        if memory_view.show_instructions {
+                            header.col(|ui| {
+                                ui.heading("Instruction");
+                            });
+                        }
                     })
                     .body(|body| {
-                        body.rows(16., self.context.memory().0.len(), |addr, mut row| {
-                            let addr = Address(U12::from_u16(addr as u16));
-                            let word = self.context.memory()[addr];
-                            let color = self
+                        body.rows(16., addresses.len(), |row_index, mut row| {
+                            let addr = addresses[row_index];
+                            let word = doc.context.memory()[addr];
+                            let color = doc
                                 .context
                                 .last_modifications()
                                 .iter()
                                 .enumerate()
+                                .filter(|_| memory_view.highlight_modifications)
                                 .find(|(_, a)| addr == **a)
                                 .map(|(idx, _)| {
                                     let color1 = egui::Rgba::from(text_color);
                                     let color2 = egui::Rgba::from(egui::Color32::RED);
-                                    let f = idx as f32 / 5.;
+                                    let f = idx as f32
+                                        / simplez_interpreter::TRACKED_MODIFICATIONS as f32;
                                     egui::Color32::from(egui::Rgba::from_rgb(
                                         color1.r() * f + color2.r() * (1. - f),
                                         color1.g() * f + color2.g() * (1. - f),
@@ -177,27 +630,81 @@ impl eframe::App for App {
                                 })
                                 .unwrap_or(text_color);
                             row.col(|ui| {
-                                let response = ui.monospace(format!("[{}]", addr));
+                                let has_breakpoint = doc.context.breakpoints.contains(&addr);
+                                let mut text = egui::RichText::new(format!(
+                                    "[/{}]",
+                                    self.formats.address.format(addr.0)
+                                ))
+                                .monospace();
+                                if has_breakpoint {
+                                    text = text.color(ui.visuals().error_fg_color);
+                                }
+                                let response = ui
+                                    .add(egui::Label::new(text).sense(egui::Sense::click()))
+                                    .on_hover_text("Click to toggle a breakpoint");
+                                if response.clicked() {
+                                    doc.context.toggle_breakpoint(addr);
+                                }
 
-                                if addr == self.context.pc {
+                                if addr == doc.context.pc {
                                     loc_rect.min.y = response.rect.min.y;
                                     loc_rect.set_height(response.rect.height());
                                     render_loc_rect = true;
                                 }
                             });
+                            if memory_view.show_heat_map {
+                                row.col(|ui| {
+                                    let counts = doc.context.profile().counts(addr);
+                                    if counts.total() == 0 {
+                                        return;
+                                    }
+                                    ui.painter().rect_filled(
+                                        ui.max_rect(),
+                                        0.,
+                                        profile::heat_color(counts.total(), hottest),
+                                    );
+                                    ui.monospace(counts.total().to_string()).on_hover_text(
+                                        format!(
+                                            "Executed {} times, read {} times and written {} times",
+                                            counts.executions, counts.reads, counts.writes
+                                        ),
+                                    );
+                                });
+                            }
                             row.col(|ui| {
-                                ui.label(
-                                    egui::RichText::new(format!("{:012b}", u16::from(word)))
-                                        .monospace()
-                                        .color(color),
-                                );
+                                let format = self.formats.contents;
+                                let label = egui::RichText::new(format.format(word))
+                                    .monospace()
+                                    .color(color);
+                                if let Some(value) = word::editable_word(
+                                    ui,
+                                    &mut doc.word_editor,
+                                    egui::Id::new(("contents", addr)),
+                                    0o7777,
+                                    || format.format_editable(word),
+                                    label,
+                                ) {
+                                    doc.context.set_addr(addr, value);
+                                }
                             });
+                            if !memory_view.show_instructions {
+                                return;
+                            }
                             row.col(|ui| {
-                                ui.label(
+                                let label =
                                     egui::RichText::new(format!("{}", Instruction::from(word)))
                                         .monospace()
-                                        .color(color),
-                                );
+                                        .color(color);
+                                if let Some(value) = word::editable_word(
+                                    ui,
+                                    &mut doc.word_editor,
+                                    egui::Id::new(("instruction", addr)),
+                                    0o7777,
+                                    || Instruction::from(word).to_string(),
+                                    label,
+                                ) {
+                                    doc.context.set_addr(addr, value);
+                                }
                             });
                         });
                     });
@@ -218,11 +725,43 @@ impl eframe::App for App {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::warn_if_debug_build(ui);
-            ui.add_enabled_ui(!self.ran_program, |ui| {
+            if doc.ran_program {
+                ui.horizontal(|ui| {
+                    if doc.executing {
+                        ui.label("Debugging: pause execution to edit the program.");
+                        return;
+                    }
+                    if ui
+                        .button("Stop and edit")
+                        .on_hover_text("Reset the machine and edit the program")
+                        .clicked()
+                    {
+                        doc.reset();
+                    }
+                    ui.checkbox(&mut doc.hot_editing, "Edit while paused")
+                        .on_hover_text(
+                            "Reassemble the program as it is edited, keeping the registers",
+                        );
+                    ui.add_enabled(
+                        doc.hot_editing,
+                        egui::Checkbox::new(&mut self.keep_data, "Keep DATA and RES values"),
+                    );
+                });
+                if doc.layout_changed_under_pc() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "Code has moved under the PC: it no longer points at the instruction \
+                        execution was paused at.",
+                    );
+                }
+            }
+            {
                 let theme = highlighter::CodeTheme::from_memory(ui.ctx());
+                let font_id = self.settings.code_font();
+                let line_height = ui.fonts().row_height(&font_id);
 
                 let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-                    let mut layout_job = highlighter::highlight(ui.ctx(), &theme, string);
+                    let mut layout_job = highlighter::highlight(ui.ctx(), &theme, &font_id, string);
                     layout_job.wrap.max_width = wrap_width;
                     ui.fonts().layout_job(layout_job)
                 };
@@ -242,29 +781,29 @@ impl eframe::App for App {
                     .memory()
                     .caches
                     .cache::<EditorCodeLineCache>()
-                    .get(&self.program);
+                    .get(&doc.program);
 
-                fn lines_of_code_widget(lines_of_code: usize) -> impl egui::Widget {
+                fn lines_of_code_widget(
+                    lines_of_code: usize,
+                    font_id: egui::FontId,
+                    line_height: f32,
+                ) -> impl egui::Widget {
                     move |ui: &mut egui::Ui| {
                         let style = ui.style();
                         let text_color = style.visuals.widgets.noninteractive.text_color();
                         let widget_width =
                             ui.fonts().glyph_width(&egui::FontId::monospace(20.), '0') * 4.;
                         let (response, painter) = ui.allocate_painter(
-                            vec2(
-                                widget_width,
-                                highlighter::CODE_EDITOR_LINE_HEIGHT * lines_of_code as f32,
-                            ),
+                            vec2(widget_width, line_height * lines_of_code as f32),
                             egui::Sense::hover(),
                         );
                         let rect = response.rect;
                         for i in 1..=lines_of_code {
                             painter.text(
-                                rect.right_top()
-                                    + vec2(0., highlighter::CODE_EDITOR_LINE_HEIGHT * i as f32),
+                                rect.right_top() + vec2(0., line_height * i as f32),
                                 egui::Align2::RIGHT_BOTTOM,
                                 i.to_string(),
-                                egui::FontId::monospace(highlighter::CODE_EDITOR_LINE_HEIGHT),
+                                font_id.clone(),
                                 text_color,
                             );
                         }
@@ -272,31 +811,163 @@ impl eframe::App for App {
                     }
                 }
 
-                let textedit_response = egui::ScrollArea::vertical()
+                let editor_id = egui::Id::new(("code_editor", self.active));
+                // The completion popup gets the keys it uses before the editor, including Tab.
+                let completed = ui.memory().has_focus(editor_id)
+                    && Autocomplete::handle_keys(
+                        &mut doc.autocomplete,
+                        ui.ctx(),
+                        editor_id,
+                        &mut doc.program,
+                    );
+                let indented = ui.memory().has_focus(editor_id)
+                    && ui
+                        .input_mut()
+                        .consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+                    && insert_tab(
+                        ui.ctx(),
+                        editor_id,
+                        &mut doc.program,
+                        self.settings.tab_width,
+                    );
+
+                let (textedit_response, completed) = egui::ScrollArea::vertical()
                     .show(ui, |ui| {
-                        ui.horizontal_top(|ui| {
-                            ui.add(lines_of_code_widget(lines_of_code));
-                            ui.add(egui::Separator::default());
+                        let editable = doc.is_editable();
+                        let textedit_output = ui
+                            .horizontal_top(|ui| {
+                                ui.add(lines_of_code_widget(
+                                    lines_of_code,
+                                    font_id.clone(),
+                                    line_height,
+                                ));
+                                ui.add(egui::Separator::default());
 
-                            ui.add(
-                                TextEdit::multiline(&mut self.program)
+                                TextEdit::multiline(&mut doc.program)
+                                    .id(editor_id)
                                     .margin(egui::Vec2::ZERO)
                                     .code_editor()
+                                    .interactive(editable)
                                     .desired_width(ui.available_width())
                                     .desired_rows(10)
                                     .frame(false)
-                                    .layouter(&mut layouter),
-                            )
-                        })
+                                    .layouter(&mut layouter)
+                                    .show(ui)
+                            })
+                            .inner;
+                        let textedit_response = textedit_output.response.clone();
+                        if textedit_response.changed() {
+                            doc.autocomplete = textedit_output.cursor_range.and_then(|cursor| {
+                                Autocomplete::new(&doc.program, cursor.primary.ccursor.index)
+                            });
+                        } else if textedit_response.clicked() {
+                            doc.autocomplete = None;
+                        }
+                        let completed = Autocomplete::popup_ui(
+                            &mut doc.autocomplete,
+                            ui.ctx(),
+                            editor_id,
+                            &textedit_output,
+                            &mut doc.program,
+                        ) || completed;
+                        let line_rect = |line: usize| {
+                            let mut rect = textedit_response.rect;
+                            rect.min.y += line_height * line as f32;
+                            rect.set_height(line_height);
+                            rect
+                        };
+
+                        let hovered_token = textedit_response.hover_pos().and_then(|pos| {
+                            code_info::token_at(&textedit_output, &doc.program, pos)
+                        });
+                        if let Some(token) = hovered_token {
+                            let context = match doc.assembler_err {
+                                Some(_) => None,
+                                None => Some(&doc.context),
+                            };
+                            code_info::hover_ui(
+                                ui,
+                                &doc.program,
+                                &token,
+                                context,
+                                self.formats.contents,
+                            );
+
+                            // Ctrl+click goes to the definition of a label.
+                            let go_to_definition = {
+                                let input = ui.input();
+                                input.pointer.primary_clicked() && input.modifiers.command
+                            };
+                            let definition = token
+                                .label_name(&doc.program)
+                                .zip(context)
+                                .and_then(|(name, context)| context.debug_info().symbol(name))
+                                .map(|symbol| symbol.line);
+                            if let (true, Some(line)) = (go_to_definition, definition) {
+                                if editable {
+                                    code_info::go_to_line(ui.ctx(), editor_id, &doc.program, line);
+                                }
+                                ui.scroll_to_rect(line_rect(line), Some(egui::Align::Center));
+                            }
+                        }
+
+                        if doc.ran_program {
+                            let debug_info = doc.context.debug_info();
+
+                            // Lines whose words were just written fade out like the memory table.
+                            for (idx, addr) in doc.context.last_modifications().iter().enumerate() {
+                                if let Some(line) = debug_info.source_line(*addr) {
+                                    let f = idx as f32
+                                        / simplez_interpreter::TRACKED_MODIFICATIONS as f32;
+                                    ui.painter().rect_filled(
+                                        line_rect(line),
+                                        1.,
+                                        egui::Color32::RED.linear_multiply(0.3 * (1. - f)),
+                                    );
+                                }
+                            }
+
+                            if let Some(line) = debug_info.source_line(doc.context.pc) {
+                                let rect = line_rect(line);
+                                ui.painter().rect_filled(
+                                    rect,
+                                    1.,
+                                    ui.style()
+                                        .visuals
+                                        .widgets
+                                        .active
+                                        .bg_fill
+                                        .linear_multiply(0.7),
+                                );
+                                if doc.scrolled_to_line != Some(line) {
+                                    doc.scrolled_to_line = Some(line);
+                                    ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                }
+                            }
+                        }
+
+                        (textedit_response, completed)
                     })
-                    .inner
                     .inner;
 
-                if let Some(err) = &mut self.assembler_err {
+                for warning in &doc.warnings {
+                    let mut warning_rect = textedit_response.rect;
+                    warning_rect.min.y += line_height * warning.line as f32;
+                    warning_rect.set_height(line_height);
+
+                    ui.allocate_rect(warning_rect, egui::Sense::hover())
+                        .on_hover_text(format!("{} [{}]", warning.message, warning.lint));
+                    ui.painter().rect_filled(
+                        warning_rect,
+                        1.,
+                        ui.style().visuals.warn_fg_color.linear_multiply(0.1),
+                    );
+                }
+
+                if let Some(err) = &mut doc.assembler_err {
                     let mut error_rect = textedit_response.rect;
-                    error_rect.min.y =
-                        error_rect.min.y + highlighter::CODE_EDITOR_LINE_HEIGHT * err.loc as f32;
-                    error_rect.set_height(highlighter::CODE_EDITOR_LINE_HEIGHT);
+                    error_rect.min.y = error_rect.min.y + line_height * err.loc as f32;
+                    error_rect.set_height(line_height);
 
                     ui.allocate_rect(error_rect, egui::Sense::hover())
                         .on_hover_text(&err.description);
@@ -307,58 +978,198 @@ impl eframe::App for App {
                     );
                 }
 
-                if textedit_response.changed() {
-                    self.assemble_program();
+                if textedit_response.changed() || indented || completed {
+                    doc.unsaved_changes = true;
+                    if doc.ran_program {
+                        doc.hot_reassemble(self.keep_data);
+                    } else {
+                        doc.assemble_program();
+                    }
                 }
-            });
-            if self.ran_program {
-                egui::Window::new("Reassemble program")
-                    .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
-                    .title_bar(false)
-                    .show(ctx, |ui| {
-                        ui.vertical_centered(|ui| {
-                            ui.heading("Program has been ran since last assembly.");
-                            if ui.button("Click here to reassemble.").clicked() {
-                                self.ran_program = false;
-                                self.executing = false;
-                                self.context.reset_registers();
-                                self.assemble_program();
-                            }
-                        })
-                    });
             }
         });
 
-        if self.executing {
-            self.ran_program = true;
-            self.context.step();
-            ctx.request_repaint();
+        let steps = if self
+            .documents
+            .iter()
+            .any(|doc| doc.executing && !doc.running_to_halt)
+        {
+            self.run_speed.steps_for_frame(ctx.input().stable_dt)
+        } else {
+            0
+        };
+        for doc in &mut self.documents {
+            if doc.execute(steps) {
+                ctx.request_repaint();
+            }
         }
     }
 }
 
 impl App {
-    fn assemble_program(&mut self) {
-        match simplez_assembler::assemble(&self.program) {
-            Ok(res) => {
-                self.context.set_memory(res);
-                self.assembler_err = None;
+    fn file_menu_ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("New").clicked() {
+            ui.close_menu();
+            self.new_document();
+        }
+        if ui.button("Open...").clicked() {
+            ui.close_menu();
+            self.file_dialogs.open(ui.ctx());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.add_enabled_ui(!self.recent_files.is_empty(), |ui| {
+            ui.menu_button("Open Recent", |ui| {
+                for path in self.recent_files.clone() {
+                    if ui.button(&path).clicked() {
+                        ui.close_menu();
+                        match file::read(&path) {
+                            Ok(file) => self.open_document(file.path, file.contents),
+                            Err(err) => {
+                                self.recent_files.retain(|recent| recent != &path);
+                                self.file_error = Some(err);
+                            }
+                        }
+                    }
+                }
+            });
+        });
+        ui.separator();
+        if ui.button("Save").clicked() {
+            ui.close_menu();
+            self.save_program(false);
+        }
+        if ui.button("Save As...").clicked() {
+            ui.close_menu();
+            self.save_program(true);
+        }
+        #[cfg(target_arch = "wasm32")]
+        if ui
+            .button("Copy Share Link")
+            .on_hover_text("Copy a link that opens this program")
+            .clicked()
+        {
+            ui.close_menu();
+            if let Some(link) = crate::share::link(&self.documents[self.active].program) {
+                ui.output().copied_text = link;
             }
-            Err(err) => {
-                let characters_consumed = self.program.len() - err.input.len();
-
-                let newlines = self
-                    .program
-                    .chars()
-                    .take(characters_consumed)
-                    .filter(|x| x == &'\n')
-                    .count();
-
-                self.assembler_err = Some(AssemblerError {
-                    description: format!("{:?}", err),
-                    loc: newlines,
-                });
+        }
+        ui.separator();
+        if ui.button("Close Tab").clicked() {
+            ui.close_menu();
+            self.pending_close = Some(self.active);
+        }
+    }
+
+    fn examples_menu_ui(&mut self, ui: &mut egui::Ui) {
+        for example in examples::EXAMPLES {
+            if ui.button(example.name).clicked() {
+                ui.close_menu();
+                let mut document = Document::new(None, example.program.to_owned());
+                document.name = Some(example.file_name.to_owned());
+                self.add_document(document);
             }
+            ui.add(egui::Label::new(egui::RichText::new(example.description).small()).wrap(true));
+            ui.separator();
+        }
+    }
+
+    fn new_document(&mut self) {
+        self.documents.push(Document::default());
+        self.active = self.documents.len() - 1;
+    }
+
+    /// Shows a program read from `path` in a tab, or switches to the tab it is already open in.
+    fn open_document(&mut self, path: String, program: String) {
+        self.add_recent_file(path.clone());
+        if let Some(index) = self
+            .documents
+            .iter()
+            .position(|doc| doc.file_path.as_ref() == Some(&path))
+        {
+            self.active = index;
+            return;
         }
+
+        self.add_document(Document::new(Some(path), program));
     }
+
+    /// Shows `document` in a new tab, or in the current one if nothing has been typed in it.
+    fn add_document(&mut self, document: Document) {
+        if self.documents[self.active].is_blank() {
+            self.documents[self.active] = document;
+        } else {
+            self.documents.push(document);
+            self.active = self.documents.len() - 1;
+        }
+    }
+
+    fn close_document(&mut self, index: usize) {
+        self.pending_close = None;
+        self.documents.remove(index);
+        if self.documents.is_empty() {
+            self.documents.push(Document::default());
+        }
+        if self.active > index || self.active == self.documents.len() {
+            self.active -= 1;
+        }
+    }
+
+    /// Saves the current tab's program to the path it was opened from, or to one picked by the
+    /// user if there is none or `save_as` is set.
+    fn save_program(&mut self, save_as: bool) {
+        let doc = &self.documents[self.active];
+        let path = match &doc.file_path {
+            Some(path) if !save_as => path.clone(),
+            path => match file::pick_save_path(path.as_deref().or(doc.name.as_deref())) {
+                Some(path) => path,
+                None => return,
+            },
+        };
+        match file::save(&path, &doc.program) {
+            Ok(()) => {
+                let doc = &mut self.documents[self.active];
+                doc.unsaved_changes = false;
+                doc.file_path = Some(path.clone());
+                self.add_recent_file(path);
+            }
+            Err(err) => self.file_error = Some(err),
+        }
+    }
+
+    fn add_recent_file(&mut self, path: String) {
+        self.recent_files.retain(|recent| recent != &path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+}
+
+/// Replaces the selection of the text edit with `id` by enough spaces to reach the next multiple
+/// of `tab_width` columns. Returns whether `text` was changed.
+fn insert_tab(ctx: &egui::Context, id: egui::Id, text: &mut String, tab_width: usize) -> bool {
+    let mut state = match egui::text_edit::TextEditState::load(ctx, id) {
+        Some(state) => state,
+        None => return false,
+    };
+    let [start, end] = match state.ccursor_range() {
+        Some(range) => range.sorted(),
+        None => return false,
+    };
+    let byte_index = |char_index: usize| {
+        text.char_indices()
+            .nth(char_index)
+            .map_or(text.len(), |(idx, _)| idx)
+    };
+    let (start_byte, end_byte) = (byte_index(start.index), byte_index(end.index));
+
+    let line_start = text[..start_byte].rfind('\n').map_or(0, |idx| idx + 1);
+    let column = text[line_start..start_byte].chars().count();
+    let tab_width = tab_width.max(1);
+    let spaces = tab_width - column % tab_width;
+    text.replace_range(start_byte..end_byte, &" ".repeat(spaces));
+
+    state.set_ccursor_range(Some(egui::text::CCursorRange::one(
+        egui::text::CCursor::new(start.index + spaces),
+    )));
+    state.store(ctx, id);
+    true
 }
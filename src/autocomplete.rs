@@ -0,0 +1,147 @@
+//! The completion popup of the code editor.
+
+use eframe::egui;
+use simplez_assembler::completion::{self, Completions};
+
+/// The completions offered for the word before the cursor, while the popup is open.
+pub struct Autocomplete {
+    completions: Completions,
+    selected: usize,
+}
+
+impl Autocomplete {
+    /// Suggests how to complete the word before character `cursor` of `text`. Returns `None` if
+    /// there is nothing to suggest, or nothing has been typed yet where a command is expected.
+    pub fn new(text: &str, cursor: usize) -> Option<Self> {
+        let offset = text
+            .char_indices()
+            .nth(cursor)
+            .map_or(text.len(), |(idx, _)| idx);
+        let completions = completion::complete(text, offset);
+        let typed_nothing =
+            completions.span.is_empty() && !text[..completions.span.start].ends_with('/');
+        if completions.items.is_empty() || typed_nothing {
+            return None;
+        }
+        Some(Self {
+            completions,
+            selected: 0,
+        })
+    }
+
+    /// Lets the keyboard choose a completion before the text edit `id` sees the keys: the arrow
+    /// keys move the selection, Enter or Tab accept it and Escape closes the popup. Returns
+    /// whether `text` was changed.
+    pub fn handle_keys(
+        autocomplete: &mut Option<Self>,
+        ctx: &egui::Context,
+        id: egui::Id,
+        text: &mut String,
+    ) -> bool {
+        let this = match autocomplete {
+            Some(this) => this,
+            None => return false,
+        };
+        let mut input = ctx.input_mut();
+        let mut consume = |key| input.consume_key(egui::Modifiers::NONE, key);
+        let count = this.completions.items.len();
+        if consume(egui::Key::ArrowDown) {
+            this.selected = (this.selected + 1) % count;
+        }
+        if consume(egui::Key::ArrowUp) {
+            this.selected = (this.selected + count - 1) % count;
+        }
+        let accept = consume(egui::Key::Enter) || consume(egui::Key::Tab);
+        let close = consume(egui::Key::Escape);
+        drop(input);
+
+        if accept {
+            this.accept(ctx, id, text);
+        }
+        if accept || close {
+            *autocomplete = None;
+        }
+        accept
+    }
+
+    /// Shows the popup below the cursor of the text edit `id`, which was laid out as `output`.
+    /// Clicking a completion accepts it, and clicking anywhere else closes the popup. Returns
+    /// whether `text` was changed.
+    pub fn popup_ui(
+        autocomplete: &mut Option<Self>,
+        ctx: &egui::Context,
+        id: egui::Id,
+        output: &egui::text_edit::TextEditOutput,
+        text: &mut String,
+    ) -> bool {
+        let (this, cursor) = match (autocomplete.as_mut(), output.cursor_range) {
+            (Some(this), Some(cursor)) => (this, cursor),
+            _ => return false,
+        };
+        let pos = output.galley.pos_from_cursor(&cursor.primary).left_bottom()
+            + output.text_draw_pos.to_vec2();
+
+        let mut clicked = None;
+        let area = egui::Area::new(id.with("autocomplete"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(200.)
+                        .show(ui, |ui| {
+                            for (idx, item) in this.completions.items.iter().enumerate() {
+                                let selected = idx == this.selected;
+                                let response = ui
+                                    .horizontal(|ui| {
+                                        let response = ui.selectable_label(
+                                            selected,
+                                            egui::RichText::new(&item.text).monospace(),
+                                        );
+                                        ui.weak(&item.detail);
+                                        response
+                                    })
+                                    .inner;
+                                if selected {
+                                    response.scroll_to_me(None);
+                                }
+                                if response.clicked() {
+                                    clicked = Some(idx);
+                                }
+                            }
+                        });
+                });
+            });
+
+        // The editor loses focus as soon as the popup is pressed, so only close it if the
+        // pointer is elsewhere.
+        if !ctx.memory().has_focus(id) && !area.response.hovered() {
+            *autocomplete = None;
+            return false;
+        }
+        match clicked {
+            Some(idx) => {
+                this.selected = idx;
+                this.accept(ctx, id, text);
+                *autocomplete = None;
+                ctx.memory().request_focus(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the word being typed with the selected completion and moves the cursor after it.
+    fn accept(&self, ctx: &egui::Context, id: egui::Id, text: &mut String) {
+        let item = &self.completions.items[self.selected];
+        let span = self.completions.span.clone();
+        text.replace_range(span.clone(), &item.text);
+
+        let mut state = egui::text_edit::TextEditState::load(ctx, id).unwrap_or_default();
+        let cursor = text[..span.start].chars().count() + item.text.chars().count();
+        state.set_ccursor_range(Some(egui::text::CCursorRange::one(
+            egui::text::CCursor::new(cursor),
+        )));
+        state.store(ctx, id);
+    }
+}
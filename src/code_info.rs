@@ -0,0 +1,114 @@
+//! What the code editor tells about the code under the pointer: hover help and the definitions
+//! of labels.
+
+use eframe::egui;
+use simplez_assembler::keywords;
+use simplez_assembler::tokens::{self, Token, TokenKind};
+use simplez_common::{Instruction, Symbol};
+use simplez_interpreter::ExecutionContext;
+
+use crate::word::WordFormat;
+
+/// Returns the token under `pos`, in screen coordinates, of a text edit showing `code`.
+pub fn token_at(
+    output: &egui::text_edit::TextEditOutput,
+    code: &str,
+    pos: egui::Pos2,
+) -> Option<Token> {
+    let galley_pos = pos - output.text_draw_pos;
+    let cursor = output.galley.cursor_from_pos(galley_pos);
+    // The cursor is the closest gap between characters. The character under the pointer is the
+    // one before it if the pointer is left of the gap.
+    let mut char_index = cursor.ccursor.index;
+    if galley_pos.x < output.galley.pos_from_cursor(&cursor).min.x {
+        char_index = char_index.checked_sub(1)?;
+    }
+    let (byte_index, _) = code.char_indices().nth(char_index)?;
+    tokens::token_at(code, byte_index)
+}
+
+/// Shows help about `token` in a tooltip next to the pointer. `context` holds the assembled
+/// program, and is `None` if the code doesn't assemble as it is.
+pub fn hover_ui(
+    ui: &mut egui::Ui,
+    code: &str,
+    token: &Token,
+    context: Option<&ExecutionContext>,
+    format: WordFormat,
+) {
+    if let Some(name) = token.label_name(code) {
+        let symbol = context.and_then(|context| context.debug_info().symbol(name));
+        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("code_info"), |ui| {
+            ui.monospace(name);
+            match (symbol, context) {
+                (Some(symbol), Some(context)) => symbol_ui(ui, symbol, context, format),
+                _ if token.kind == (TokenKind::LabelReference { defined: false }) => {
+                    ui.colored_label(ui.visuals().error_fg_color, "Undefined label");
+                }
+                _ => {
+                    ui.label("Fix the errors in the program to see its address.");
+                }
+            }
+        });
+        return;
+    }
+
+    if let TokenKind::Mnemonic | TokenKind::Directive = token.kind {
+        let keyword = match keywords::keyword(&code[token.span.clone()]) {
+            Some(keyword) => keyword,
+            None => return,
+        };
+        let line = code[..token.span.start].matches('\n').count();
+        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("code_info"), |ui| {
+            ui.monospace(keyword.syntax);
+            ui.label(keyword.summary);
+            if let Some(encoding) = keyword.encoding() {
+                ui.label(format!("Encoding: {}", encoding));
+            }
+            let assembled = context.and_then(|context| {
+                let addr = context.debug_info().instruction_at_line(line)?;
+                Some((addr, context.memory()[addr]))
+            });
+            if let Some((addr, word)) = assembled {
+                ui.label(format!(
+                    "Assembled at {} to {:#06o}: {}",
+                    addr,
+                    u16::from(word),
+                    Instruction::from(word)
+                ));
+            }
+        });
+    }
+}
+
+fn symbol_ui(ui: &mut egui::Ui, symbol: &Symbol, context: &ExecutionContext, format: WordFormat) {
+    ui.label(format!("Address: {}", symbol.address));
+    match context.memory().0.get(usize::from(symbol.address.0)) {
+        Some(word) => {
+            ui.label(format!(
+                "Value: {} ({})",
+                format.format(*word),
+                Instruction::from(*word)
+            ));
+        }
+        None => {
+            ui.label("Outside of memory");
+        }
+    }
+    ui.weak("Ctrl+click to go to its definition");
+}
+
+/// Moves the cursor of the text edit `id` to the start of `line` and focuses it.
+pub fn go_to_line(ctx: &egui::Context, id: egui::Id, code: &str, line: usize) {
+    let mut state = egui::text_edit::TextEditState::load(ctx, id).unwrap_or_default();
+    let char_index = code
+        .split('\n')
+        .take(line)
+        .map(|line| line.chars().count() + 1)
+        .sum();
+    state.set_ccursor_range(Some(egui::text::CCursorRange::one(
+        egui::text::CCursor::new(char_index),
+    )));
+    state.store(ctx, id);
+    ctx.memory().request_focus(id);
+}
@@ -0,0 +1,129 @@
+use eframe::egui;
+use eframe::epaint::{pos2, vec2, Color32, Rect, Stroke};
+use simplez_common::cfg::{self, ControlFlowGraph};
+use simplez_common::Address;
+use simplez_interpreter::ExecutionContext;
+
+/// Space between blocks, and between the widest block and the first branch lane.
+const BLOCK_SPACING: f32 = 20.;
+/// Space between the vertical lines of branches that go to other blocks.
+const LANE_SPACING: f32 = 8.;
+
+/// Draws the basic blocks of the program in `context`'s memory in address order, joined by
+/// arrows. Execution falls through straight down, and branches loop around the right side.
+/// The block of the PC is highlighted.
+pub fn graph_ui(ui: &mut egui::Ui, context: &ExecutionContext) {
+    let memory = context.memory();
+    let debug_info = context.debug_info();
+    let cfg = ControlFlowGraph::new(memory, debug_info);
+
+    ui.horizontal(|ui| {
+        ui.label(format!("{} blocks", cfg.blocks().len()));
+        if ui
+            .button("Copy as DOT")
+            .on_hover_text("Copy the graph in Graphviz's DOT language")
+            .clicked()
+        {
+            ui.output().copied_text = cfg.to_dot(memory, debug_info);
+        }
+    });
+    ui.separator();
+
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let visuals = ui.visuals().clone();
+    let padding = ui.spacing().button_padding;
+    let galleys: Vec<_> = cfg
+        .blocks()
+        .iter()
+        .map(|block| {
+            let text: Vec<String> = block
+                .addresses()
+                .map(|addr| cfg::describe_word(memory, debug_info, addr))
+                .collect();
+            ui.fonts()
+                .layout_no_wrap(text.join("\n"), font_id.clone(), visuals.text_color())
+        })
+        .collect();
+
+    // Lay the blocks out from the top left corner, then move them to where the painter is.
+    let mut y = 0.;
+    let mut rects: Vec<Rect> = galleys
+        .iter()
+        .map(|galley| {
+            let rect = Rect::from_min_size(pos2(0., y), galley.size() + 2. * padding);
+            y = rect.bottom() + BLOCK_SPACING;
+            rect
+        })
+        .collect();
+    let blocks_width = rects.iter().map(|rect| rect.width()).fold(0., f32::max);
+    let mut lanes = 0;
+    for (idx, block) in cfg.blocks().iter().enumerate() {
+        for successor in &block.successors {
+            if !falls_through(&cfg, idx, *successor) {
+                lanes += 1;
+            }
+        }
+    }
+    let size = vec2(
+        blocks_width + BLOCK_SPACING + LANE_SPACING * lanes as f32,
+        (y - BLOCK_SPACING).max(0.),
+    );
+
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    for rect in &mut rects {
+        *rect = rect.translate(response.rect.min.to_vec2());
+    }
+
+    let pc_block = cfg.block_containing(context.pc);
+    for ((block, rect), galley) in cfg.blocks().iter().zip(&rects).zip(galleys) {
+        let fill = if Some(block) == pc_block {
+            visuals.selection.bg_fill
+        } else {
+            visuals.faint_bg_color
+        };
+        painter.rect(*rect, 2., fill, visuals.widgets.noninteractive.bg_stroke);
+        painter.galley(rect.min + padding, galley);
+    }
+
+    let stroke = Stroke::new(1., visuals.text_color());
+    let zero_stroke = Stroke::new(1., Color32::from_rgb(80, 160, 80));
+    let mut lane_x = response.rect.left() + blocks_width + BLOCK_SPACING;
+    for (idx, block) in cfg.blocks().iter().enumerate() {
+        let from = rects[idx];
+        for (successor_idx, successor) in block.successors.iter().enumerate() {
+            let to = match cfg.blocks().iter().position(|b| b.start == *successor) {
+                Some(to) => rects[to],
+                None => continue,
+            };
+            // A `BZ` lists the instruction after it first and its branch second.
+            let stroke = if successor_idx == 1 {
+                zero_stroke
+            } else {
+                stroke
+            };
+            if falls_through(&cfg, idx, *successor) {
+                painter.arrow(
+                    from.center_bottom(),
+                    to.center_top() - from.center_bottom(),
+                    stroke,
+                );
+                continue;
+            }
+
+            let start = pos2(from.right(), from.center().y);
+            let end = pos2(to.right(), to.top() + padding.y + font_id.size / 2.);
+            painter.line_segment([start, pos2(lane_x, start.y)], stroke);
+            painter.line_segment([pos2(lane_x, start.y), pos2(lane_x, end.y)], stroke);
+            painter.arrow(pos2(lane_x, end.y), end - pos2(lane_x, end.y), stroke);
+            lane_x += LANE_SPACING;
+        }
+    }
+}
+
+/// Whether execution goes from the block at `idx` to `successor` by moving on to the block right
+/// after it, rather than by branching.
+fn falls_through(cfg: &ControlFlowGraph, idx: usize, successor: Address) -> bool {
+    let block = &cfg.blocks()[idx];
+    u16::from(block.end.0) + 1 == u16::from(successor.0)
+        && cfg.blocks().get(idx + 1).map(|next| next.start) == Some(successor)
+}
@@ -0,0 +1,239 @@
+use simplez_assembler::format::FormatOptions;
+use simplez_assembler::lint::{self, LintConfig, Warning};
+use simplez_common::{DebugInfo, Memory};
+use simplez_interpreter::snapshot::Snapshot;
+use simplez_interpreter::{ExecutionContext, StepOutcome};
+
+use crate::autocomplete::Autocomplete;
+use crate::snapshots::NamedSnapshot;
+use crate::word;
+
+/// How many instructions "Run to halt" executes every frame. High enough to finish most programs
+/// in an instant, low enough to keep the UI responsive while running infinite loops.
+pub const RUN_TO_HALT_STEPS_PER_FRAME: usize = 100_000;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct AssemblerError {
+    pub loc: usize,
+    pub description: String,
+}
+
+impl AssemblerError {
+    fn new(program: &str, err: simplez_assembler::Error<&str>) -> Self {
+        Self {
+            description: format!("{:?}", err),
+            loc: simplez_assembler::line_number(program, err.input),
+        }
+    }
+}
+
+/// A program open in one of the editor tabs, along with the machine it is assembled into.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Document {
+    pub program: String,
+    /// Where `program` was last opened from or saved to.
+    pub file_path: Option<String>,
+    /// The name to show and suggest when saving, if `file_path` is not set yet.
+    pub name: Option<String>,
+    /// Whether `program` has been edited since it was last opened or saved.
+    pub unsaved_changes: bool,
+    pub assembler_err: Option<AssemblerError>,
+    /// What the linter found in the program when it was last assembled.
+    #[serde(skip)]
+    pub warnings: Vec<Warning>,
+    pub context: ExecutionContext,
+    /// The machine as it was at moments the user chose to save, to go back to later.
+    pub snapshots: Vec<NamedSnapshot>,
+    /// The memory pinned to compare the machine with in the memory diff window.
+    #[serde(skip)]
+    pub memory_baseline: Option<Memory>,
+
+    #[serde(skip)]
+    pub executing: bool,
+    /// Set while executing with "Run to halt", which ignores the run speed.
+    #[serde(skip)]
+    pub running_to_halt: bool,
+    #[serde(skip)]
+    pub ran_program: bool,
+    /// Why execution last stopped, shown below the execution controls.
+    #[serde(skip)]
+    pub last_outcome: Option<StepOutcome>,
+    #[serde(skip)]
+    pub word_editor: Option<word::WordEditor>,
+    /// The completion popup of the code editor, while it is open.
+    #[serde(skip)]
+    pub autocomplete: Option<Autocomplete>,
+    /// The line of the PC the editor last scrolled to while debugging, so that it only scrolls
+    /// again once the PC moves to another line.
+    #[serde(skip)]
+    pub scrolled_to_line: Option<usize>,
+    /// Whether the program can be edited and reassembled while the machine is paused.
+    #[serde(skip)]
+    pub hot_editing: bool,
+    /// The debug info of the program as it was when the machine was paused, to tell whether
+    /// reassembling it has moved code under the PC.
+    #[serde(skip)]
+    edit_base: Option<DebugInfo>,
+}
+
+impl Document {
+    pub fn new(path: Option<String>, program: String) -> Self {
+        let mut document = Self {
+            program,
+            file_path: path,
+            ..Default::default()
+        };
+        document.assemble_program();
+        document
+    }
+
+    /// The name shown in the document's tab: its file name, or "Untitled" if it was never saved.
+    pub fn title(&self) -> String {
+        let name = match (&self.file_path, &self.name) {
+            (Some(path), _) => path.rsplit(['/', '\\']).next().unwrap_or(path),
+            (None, Some(name)) => name,
+            (None, None) => "Untitled",
+        };
+        format!("{}{}", name, if self.unsaved_changes { "*" } else { "" })
+    }
+
+    /// Whether this is a new document nobody has typed in yet, which can be replaced by a
+    /// program being opened instead of opening it in a new tab.
+    pub fn is_blank(&self) -> bool {
+        self.file_path.is_none() && self.program.is_empty() && !self.unsaved_changes
+    }
+
+    /// Whether the program can be edited: before running it, or while paused with "Edit while
+    /// paused" on.
+    pub fn is_editable(&self) -> bool {
+        !self.ran_program || (self.hot_editing && !self.executing)
+    }
+
+    /// Reformats the program with [`simplez_assembler::format::format`]. Returns whether it
+    /// changed, in which case it needs reassembling.
+    pub fn format(&mut self, options: FormatOptions) -> bool {
+        let formatted = simplez_assembler::format::format(&self.program, options);
+        if formatted == self.program {
+            return false;
+        }
+        self.program = formatted;
+        self.unsaved_changes = true;
+        self.autocomplete = None;
+        true
+    }
+
+    /// Stops execution and reassembles the program into a machine with its registers reset.
+    pub fn reset(&mut self) {
+        self.executing = false;
+        self.running_to_halt = false;
+        self.ran_program = false;
+        self.last_outcome = None;
+        self.scrolled_to_line = None;
+        self.hot_editing = false;
+        self.edit_base = None;
+        self.context.reset_registers();
+        self.assemble_program();
+    }
+
+    /// Executes up to `steps` instructions if the document is running, or as many as "Run to
+    /// halt" allows. Returns whether it is still running afterwards.
+    pub fn execute(&mut self, steps: usize) -> bool {
+        if !self.executing {
+            return false;
+        }
+        self.ran_program = true;
+        self.edit_base = None;
+        let steps = if self.running_to_halt {
+            RUN_TO_HALT_STEPS_PER_FRAME
+        } else {
+            steps
+        };
+        match self.context.run(steps) {
+            StepOutcome::StepLimitReached => true,
+            outcome => {
+                self.executing = false;
+                self.running_to_halt = false;
+                self.last_outcome = Some(outcome);
+                false
+            }
+        }
+    }
+
+    /// Saves `snapshot` to the slot called `name`, replacing the one there if any.
+    pub fn add_snapshot(&mut self, name: String, snapshot: Snapshot) {
+        match self.snapshots.iter_mut().find(|slot| slot.name == name) {
+            Some(slot) => slot.snapshot = snapshot,
+            None => self.snapshots.push(NamedSnapshot { name, snapshot }),
+        }
+    }
+
+    /// Puts the machine back in the state of `snapshot`, paused. The program counts as having
+    /// run, so that it isn't reassembled over the restored memory until it is reset.
+    pub fn restore_snapshot(&mut self, snapshot: Snapshot) {
+        self.executing = false;
+        self.running_to_halt = false;
+        self.ran_program = true;
+        self.last_outcome = None;
+        self.scrolled_to_line = None;
+        self.edit_base = None;
+        self.context.restore(snapshot);
+    }
+
+    pub fn step(&mut self) {
+        self.ran_program = true;
+        self.edit_base = None;
+        self.last_outcome = match self.context.step() {
+            StepOutcome::Continue => None,
+            outcome => Some(outcome),
+        };
+    }
+
+    pub fn assemble_program(&mut self) {
+        match simplez_assembler::assemble_with_debug_info(&self.program) {
+            Ok((memory, debug_info)) => {
+                self.lint(&memory, &debug_info);
+                self.context.set_memory(memory);
+                self.context.set_debug_info(debug_info);
+                self.assembler_err = None;
+            }
+            Err(err) => {
+                self.assembler_err = Some(AssemblerError::new(&self.program, err));
+                self.warnings.clear();
+            }
+        }
+    }
+
+    /// Assembles the edited program into the paused machine without resetting its registers.
+    /// See [`ExecutionContext::reload_program`].
+    pub fn hot_reassemble(&mut self, keep_data: bool) {
+        match simplez_assembler::assemble_with_debug_info(&self.program) {
+            Ok((memory, debug_info)) => {
+                self.lint(&memory, &debug_info);
+                if self.edit_base.is_none() {
+                    self.edit_base = Some(self.context.debug_info().clone());
+                }
+                self.context.reload_program(memory, debug_info, keep_data);
+                self.assembler_err = None;
+            }
+            Err(err) => {
+                self.assembler_err = Some(AssemblerError::new(&self.program, err));
+                self.warnings.clear();
+            }
+        }
+    }
+
+    fn lint(&mut self, memory: &Memory, debug_info: &DebugInfo) {
+        let config = LintConfig::from_source(&self.program);
+        self.warnings = lint::lint_program(&self.program, memory, debug_info, &config);
+    }
+
+    /// Whether the program was edited while paused in a way that moved code under the PC, so
+    /// that it no longer points at the instruction it was paused at.
+    pub fn layout_changed_under_pc(&self) -> bool {
+        match &self.edit_base {
+            Some(base) => !base.same_statement_at(self.context.debug_info(), self.context.pc),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+/// A program bundled with the app, listed in the Examples menu.
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Suggested when the example is saved for the first time.
+    pub file_name: &'static str,
+    pub program: &'static str,
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "Fibonacci",
+        description: "Adds up the first K terms of the Fibonacci sequence into SUMA.",
+        file_name: "fib.sz",
+        program: include_str!("../examples/fib.sz"),
+    },
+    Example {
+        name: "Multiplication",
+        description: "Multiplies A by B by adding A to itself B times, leaving the result in PROD.",
+        file_name: "multiply.sz",
+        program: include_str!("../examples/multiply.sz"),
+    },
+    Example {
+        name: "Memory copy",
+        description: "Copies N words from SRC to DST by rewriting its own LD and ST instructions.",
+        file_name: "copy.sz",
+        program: include_str!("../examples/copy.sz"),
+    },
+    Example {
+        name: "Scrolling worm",
+        description: "Keeps copying a block of memory forward, so it crawls through the memory \
+            table. Untick \"PC wraps around to /0\" to let it run past the end of memory.",
+        file_name: "worm.sz",
+        program: include_str!("../examples/worm.sz"),
+    },
+    Example {
+        name: "I/O echo",
+        description: "Copies every key pressed to the screen through the ports at /508-/511. \
+            Devices are not emulated yet: type a character into KBDATA (/509) and set KBST \
+            (/508) to 1 in the memory table to simulate a key press.",
+        file_name: "echo.sz",
+        program: include_str!("../examples/echo.sz"),
+    },
+];
+
+#[cfg(test)]
+#[test]
+fn examples_assemble() {
+    for example in EXAMPLES {
+        if let Err(err) = simplez_assembler::assemble(example.program) {
+            panic!("{} does not assemble: {:?}", example.name, err);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+
+const FILTER_NAME: &str = "Simplez assembly";
+const EXTENSIONS: &[&str] = &["sz", "txt"];
+const SNAPSHOT_FILTER_NAME: &str = "Simplez snapshot";
+const SNAPSHOT_EXTENSIONS: &[&str] = &["json"];
+/// Name suggested when saving a program that was never saved before.
+const DEFAULT_FILE_NAME: &str = "program.sz";
+
+/// A program read from disk, or uploaded on the web.
+pub struct OpenedFile {
+    /// The full path on desktop, or just the file name on the web.
+    pub path: String,
+    pub contents: String,
+}
+
+/// Asks the user for files to open. On the web the file picker is asynchronous, so the file
+/// is handed over in a later frame by [`Self::take_opened`].
+#[derive(Default)]
+pub struct FileDialogs {
+    opened: Slot,
+    opened_snapshot: Slot,
+}
+
+type Slot = Arc<Mutex<Option<Result<OpenedFile, String>>>>;
+
+impl FileDialogs {
+    pub fn open(&self, ctx: &egui::Context) {
+        pick(ctx, FILTER_NAME, EXTENSIONS, &self.opened);
+    }
+
+    /// Returns the file picked with [`Self::open`] once it has been read.
+    pub fn take_opened(&self) -> Option<Result<OpenedFile, String>> {
+        self.opened.lock().unwrap().take()
+    }
+
+    /// Asks the user for a snapshot file to import.
+    pub fn open_snapshot(&self, ctx: &egui::Context) {
+        pick(
+            ctx,
+            SNAPSHOT_FILTER_NAME,
+            SNAPSHOT_EXTENSIONS,
+            &self.opened_snapshot,
+        );
+    }
+
+    /// Returns the file picked with [`Self::open_snapshot`] once it has been read.
+    pub fn take_opened_snapshot(&self) -> Option<Result<OpenedFile, String>> {
+        self.opened_snapshot.lock().unwrap().take()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn pick(_ctx: &egui::Context, filter_name: &str, extensions: &[&str], slot: &Slot) {
+    if let Some(path) = rfd::FileDialog::new()
+        .add_filter(filter_name, extensions)
+        .pick_file()
+    {
+        *slot.lock().unwrap() = Some(read(&path.to_string_lossy()));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick(ctx: &egui::Context, filter_name: &str, extensions: &[&str], slot: &Slot) {
+    let dialog = rfd::AsyncFileDialog::new().add_filter(filter_name, extensions);
+    let slot = slot.clone();
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Some(file) = dialog.pick_file().await {
+            let result = String::from_utf8(file.read().await)
+                .map(|contents| OpenedFile {
+                    path: file.file_name(),
+                    contents,
+                })
+                .map_err(|err| format!("Could not read {}: {}", file.file_name(), err));
+            *slot.lock().unwrap() = Some(result);
+            ctx.request_repaint();
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &str) -> Result<OpenedFile, String> {
+    std::fs::read_to_string(path)
+        .map(|contents| OpenedFile {
+            path: path.to_owned(),
+            contents,
+        })
+        .map_err(|err| format!("Could not read {}: {}", path, err))
+}
+
+/// Asks the user where to save a program, starting at `path`. Returns `None` if they cancel.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_save_path(path: Option<&str>) -> Option<String> {
+    let path = std::path::Path::new(path.unwrap_or(DEFAULT_FILE_NAME));
+    let mut dialog = rfd::FileDialog::new().add_filter(FILTER_NAME, EXTENSIONS);
+    if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+        dialog = dialog.set_directory(dir);
+    }
+    if let Some(name) = path.file_name() {
+        dialog = dialog.set_file_name(&name.to_string_lossy());
+    }
+    dialog
+        .save_file()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Browsers always save downloads to their own folder, so there is no path to pick.
+#[cfg(target_arch = "wasm32")]
+pub fn pick_save_path(path: Option<&str>) -> Option<String> {
+    Some(path.unwrap_or(DEFAULT_FILE_NAME).to_owned())
+}
+
+/// Asks the user where to save a file that isn't a program, suggesting `name`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_export_path(name: &str, filter_name: &str, extension: &str) -> Option<String> {
+    rfd::FileDialog::new()
+        .add_filter(filter_name, &[extension])
+        .set_file_name(name)
+        .save_file()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn pick_export_path(name: &str, _filter_name: &str, _extension: &str) -> Option<String> {
+    Some(name.to_owned())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(path: &str, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|err| format!("Could not save {}: {}", path, err))
+}
+
+/// Makes the browser download `contents` as a file named `path`.
+#[cfg(target_arch = "wasm32")]
+pub fn save(path: &str, contents: &str) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let error = |err: wasm_bindgen::JsValue| format!("Could not download {}: {:?}", path, err);
+    let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(contents));
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(
+        &parts,
+        web_sys::BlobPropertyBag::new().type_("text/plain"),
+    )
+    .map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+
+    let anchor = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| format!("Could not download {}: no document", path))?
+        .create_element("a")
+        .map_err(error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|element| error(element.into()))?;
+    anchor.set_href(&url);
+    anchor.set_download(path);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).map_err(error)
+}
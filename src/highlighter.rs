@@ -1,22 +1,27 @@
 use eframe::egui;
 use egui::text::LayoutJob;
-use syntect::parsing::SyntaxDefinition;
 
 type HighlightCache = egui::util::cache::FrameCache<LayoutJob, Highlighter>;
 
-pub const CODE_EDITOR_LINE_HEIGHT: f32 = 16.;
-
 /// Memoized Code highlighting
-pub fn highlight(ctx: &egui::Context, theme: &CodeTheme, code: &str) -> LayoutJob {
-    impl egui::util::cache::ComputerMut<(&CodeTheme, &str), LayoutJob> for Highlighter {
-        fn compute(&mut self, (theme, code): (&CodeTheme, &str)) -> LayoutJob {
-            self.highlight(theme, code, "sz")
+pub fn highlight(
+    ctx: &egui::Context,
+    theme: &CodeTheme,
+    font_id: &egui::FontId,
+    code: &str,
+) -> LayoutJob {
+    impl egui::util::cache::ComputerMut<(&CodeTheme, &egui::FontId, &str), LayoutJob> for Highlighter {
+        fn compute(
+            &mut self,
+            (theme, font_id, code): (&CodeTheme, &egui::FontId, &str),
+        ) -> LayoutJob {
+            self.highlight(theme, font_id, code)
         }
     }
 
     let mut memory = ctx.memory();
     let highlight_cache = memory.caches.cache::<HighlightCache>();
-    highlight_cache.get((theme, code))
+    highlight_cache.get((theme, font_id, code))
 }
 
 #[derive(Clone, Copy, Hash, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -152,98 +157,90 @@ impl CodeTheme {
 }
 
 struct Highlighter {
-    ps: syntect::parsing::SyntaxSet,
     ts: syntect::highlighting::ThemeSet,
 }
 
 impl Default for Highlighter {
     fn default() -> Self {
         Self {
-            ps: {
-                let mut builder = syntect::parsing::SyntaxSetBuilder::new();
-                builder.add(
-                    SyntaxDefinition::load_from_str(include_str!("../syntax.yaml"), true, None)
-                        .unwrap(),
-                );
-                builder.build()
-            },
             ts: syntect::highlighting::ThemeSet::load_defaults(),
         }
     }
 }
 
 impl Highlighter {
-    #[allow(clippy::unused_self, clippy::unnecessary_wraps)]
-    fn highlight(&self, theme: &CodeTheme, code: &str, lang: &str) -> LayoutJob {
-        self.highlight_impl(theme, code, lang).unwrap_or_else(|| {
-            // Fallback:
-            LayoutJob::simple(
-                code.into(),
-                egui::FontId::monospace(CODE_EDITOR_LINE_HEIGHT),
-                if theme.dark_mode {
-                    egui::Color32::LIGHT_GRAY
-                } else {
-                    egui::Color32::DARK_GRAY
-                },
-                f32::INFINITY,
-            )
-        })
-    }
-
-    fn highlight_impl(&self, theme: &CodeTheme, text: &str, language: &str) -> Option<LayoutJob> {
-        use syntect::easy::HighlightLines;
+    /// Colors `code` with the tokens found by the assembler's tokenizer, taking the color of each
+    /// kind of token from the scope that best describes it in the syntect theme.
+    fn highlight(&self, theme: &CodeTheme, font_id: &egui::FontId, code: &str) -> LayoutJob {
+        use egui::text::{LayoutSection, TextFormat};
+        use simplez_assembler::tokens::{self, TokenKind};
         use syntect::highlighting::FontStyle;
-        use syntect::util::LinesWithEndings;
+        use syntect::parsing::Scope;
 
-        let syntax = self
-            .ps
-            .find_syntax_by_name(language)
-            .or_else(|| self.ps.find_syntax_by_extension(language))?;
-
-        let theme = theme.syntect_theme.syntect_key_name();
-        let mut h = HighlightLines::new(syntax, &self.ts.themes[theme]);
-
-        use egui::text::{LayoutSection, TextFormat};
+        let highlighter = syntect::highlighting::Highlighter::new(
+            &self.ts.themes[theme.syntect_theme.syntect_key_name()],
+        );
+        let error_color = if theme.dark_mode {
+            egui::Visuals::dark().error_fg_color
+        } else {
+            egui::Visuals::light().error_fg_color
+        };
+        let format = |kind: Option<TokenKind>| {
+            let scope = match kind {
+                Some(TokenKind::LabelDefinition | TokenKind::LabelReference { .. }) => {
+                    Some("entity.name.function")
+                }
+                Some(TokenKind::Mnemonic) => Some("keyword.control"),
+                Some(TokenKind::Directive) => Some("storage.type"),
+                Some(TokenKind::Address | TokenKind::Number) => Some("constant.numeric"),
+                Some(TokenKind::Comment) => Some("comment"),
+                Some(TokenKind::Error) | None => None,
+            };
+            let style = match scope.and_then(|scope| Scope::new(scope).ok()) {
+                Some(scope) => highlighter.style_for_stack(&[scope]),
+                None => highlighter.get_default(),
+            };
+            let fg = style.foreground;
+            let mut format = TextFormat {
+                font_id: font_id.clone(),
+                color: egui::Color32::from_rgb(fg.r, fg.g, fg.b),
+                italics: style.font_style.contains(FontStyle::ITALIC),
+                ..Default::default()
+            };
+            match kind {
+                Some(TokenKind::Error) => format.color = error_color,
+                Some(TokenKind::LabelReference { defined: false }) => {
+                    format.underline = egui::Stroke::new(1.0, error_color);
+                }
+                _ => (),
+            }
+            format
+        };
 
         let mut job = LayoutJob {
-            text: text.into(),
+            text: code.into(),
             ..Default::default()
         };
+        let mut section = |byte_range: std::ops::Range<usize>, kind: Option<TokenKind>| {
+            job.sections.push(LayoutSection {
+                leading_space: 0.0,
+                byte_range,
+                format: format(kind),
+            });
+        };
 
-        for line in LinesWithEndings::from(text) {
-            for (style, range) in h.highlight_line(line, &self.ps).ok()? {
-                let fg = style.foreground;
-                let text_color = egui::Color32::from_rgb(fg.r, fg.g, fg.b);
-                let italics = style.font_style.contains(FontStyle::ITALIC);
-                let underline = style.font_style.contains(FontStyle::ITALIC);
-                let underline = if underline {
-                    egui::Stroke::new(1.0, text_color)
-                } else {
-                    egui::Stroke::none()
-                };
-                job.sections.push(LayoutSection {
-                    leading_space: 0.0,
-                    byte_range: as_byte_range(text, range),
-                    format: TextFormat {
-                        font_id: egui::FontId::monospace(CODE_EDITOR_LINE_HEIGHT),
-                        color: text_color,
-                        italics,
-                        underline,
-                        ..Default::default()
-                    },
-                });
+        let mut end = 0;
+        for token in tokens::tokenize(code) {
+            if end < token.span.start {
+                section(end..token.span.start, None);
             }
+            end = token.span.end;
+            section(token.span, Some(token.kind));
+        }
+        if end < code.len() {
+            section(end..code.len(), None);
         }
 
-        Some(job)
+        job
     }
 }
-
-fn as_byte_range(whole: &str, range: &str) -> std::ops::Range<usize> {
-    let whole_start = whole.as_ptr() as usize;
-    let range_start = range.as_ptr() as usize;
-    assert!(whole_start <= range_start);
-    assert!(range_start + range.len() <= whole_start + whole.len());
-    let offset = range_start - whole_start;
-    offset..(offset + range.len())
-}
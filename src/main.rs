@@ -2,7 +2,21 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 mod app;
+mod autocomplete;
+mod code_info;
+mod control_flow;
+mod document;
+mod examples;
+mod file;
 mod highlighter;
+mod memory_diff;
+mod profile;
+mod settings;
+#[cfg(target_arch = "wasm32")]
+mod share;
+mod snapshots;
+mod trace;
+mod word;
 
 use app::App;
 
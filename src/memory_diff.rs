@@ -0,0 +1,129 @@
+use eframe::egui;
+use simplez_common::diff;
+
+use crate::document::Document;
+use crate::word::WordFormat;
+
+/// What the baseline is compared with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum Target {
+    /// The memory of the machine as it runs.
+    #[default]
+    Live,
+    /// The memory saved in the snapshot slot with this name.
+    Snapshot(String),
+}
+
+/// The memory diff window, comparing the memory pinned as the document's baseline with the live
+/// machine or one of its snapshots.
+#[derive(Default)]
+pub struct MemoryDiffView {
+    target: Target,
+}
+
+impl MemoryDiffView {
+    /// Shows the changed words, with their addresses in `address_format` and their values in
+    /// `contents_format`.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        doc: &mut Document,
+        address_format: WordFormat,
+        contents_format: WordFormat,
+    ) {
+        if let Target::Snapshot(name) = &self.target {
+            if !doc.snapshots.iter().any(|slot| &slot.name == name) {
+                self.target = Target::Live;
+            }
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .button("Pin baseline")
+                .on_hover_text("Compare with the memory as it is now")
+                .clicked()
+            {
+                doc.memory_baseline = Some(doc.context.memory().clone());
+            }
+            if ui
+                .add_enabled(doc.memory_baseline.is_some(), egui::Button::new("Unpin"))
+                .clicked()
+            {
+                doc.memory_baseline = None;
+            }
+            let selected = match &self.target {
+                Target::Live => "Live machine",
+                Target::Snapshot(name) => name,
+            };
+            egui::ComboBox::from_label("Compare with")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.target, Target::Live, "Live machine");
+                    for slot in &doc.snapshots {
+                        let target = Target::Snapshot(slot.name.clone());
+                        ui.selectable_value(&mut self.target, target, &slot.name);
+                    }
+                });
+        });
+        ui.separator();
+
+        let baseline = match &doc.memory_baseline {
+            Some(baseline) => baseline,
+            None => {
+                ui.label("Pin the memory as a baseline to see what running the program changes.");
+                return;
+            }
+        };
+        let (memory, debug_info) = match &self.target {
+            Target::Live => (doc.context.memory(), doc.context.debug_info()),
+            Target::Snapshot(name) => {
+                let slot = doc
+                    .snapshots
+                    .iter()
+                    .find(|slot| &slot.name == name)
+                    .expect("missing snapshots are no longer compared with");
+                (&slot.snapshot.memory, &slot.snapshot.debug_info)
+            }
+        };
+        let changes = diff::compare(baseline, memory, debug_info);
+        ui.label(match changes.len() {
+            0 => "No words changed".to_string(),
+            1 => "1 word changed".to_string(),
+            len => format!("{} words changed", len),
+        });
+
+        let heading_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui_extras::TableBuilder::new(ui)
+            .striped(true)
+            .column(egui_extras::Size::remainder().at_least(100.))
+            .columns(egui_extras::Size::exact(110.), 4)
+            .header(heading_height, |mut header| {
+                for title in ["Word", "Old", "New", "Old instruction", "New instruction"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(16., changes.len(), |row_index, mut row| {
+                    let change = &changes[row_index];
+                    row.col(|ui| {
+                        let mut word = format!("[/{}]", address_format.format(change.address.0));
+                        if let Some(label) = &change.label {
+                            word = format!("{} {}", word, label);
+                        }
+                        ui.monospace(word);
+                    });
+                    for text in [
+                        contents_format.format(change.old),
+                        contents_format.format(change.new),
+                        change.old_instruction().to_string(),
+                        change.new_instruction().to_string(),
+                    ] {
+                        row.col(|ui| {
+                            ui.monospace(text);
+                        });
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,102 @@
+use eframe::egui;
+use simplez_common::cfg;
+use simplez_interpreter::profile::Counts;
+use simplez_interpreter::ExecutionContext;
+
+/// Which count the hottest words are sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SortBy {
+    #[default]
+    Total,
+    Executions,
+    Reads,
+    Writes,
+}
+
+impl SortBy {
+    fn count(self, counts: &Counts) -> u64 {
+        match self {
+            SortBy::Total => counts.total(),
+            SortBy::Executions => counts.executions,
+            SortBy::Reads => counts.reads,
+            SortBy::Writes => counts.writes,
+        }
+    }
+}
+
+/// The profile window, listing the words the program used most since it was last reset.
+#[derive(Default)]
+pub struct ProfileView {
+    sort_by: SortBy,
+}
+
+impl ProfileView {
+    pub fn ui(&mut self, ui: &mut egui::Ui, context: &ExecutionContext) {
+        let profile = context.profile();
+        let totals = profile.totals();
+        ui.label(format!(
+            "{} instructions executed, {} reads and {} writes",
+            totals.executions, totals.reads, totals.writes
+        ));
+        ui.separator();
+
+        let mut hottest = profile.hottest();
+        let sort_by = self.sort_by;
+        hottest.sort_by(|(a, a_counts), (b, b_counts)| {
+            sort_by
+                .count(b_counts)
+                .cmp(&sort_by.count(a_counts))
+                .then(a.cmp(b))
+        });
+
+        let heading_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui_extras::TableBuilder::new(ui)
+            .striped(true)
+            .column(egui_extras::Size::remainder().at_least(120.))
+            .columns(egui_extras::Size::exact(70.), 4)
+            .header(heading_height, |mut header| {
+                header.col(|ui| {
+                    ui.strong("Word");
+                });
+                for (sort_by, title) in [
+                    (SortBy::Executions, "Executed"),
+                    (SortBy::Reads, "Read"),
+                    (SortBy::Writes, "Written"),
+                    (SortBy::Total, "Total"),
+                ] {
+                    header.col(|ui| {
+                        ui.selectable_value(&mut self.sort_by, sort_by, title)
+                            .on_hover_text("Sort by this count");
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(16., hottest.len(), |row_index, mut row| {
+                    let (addr, counts) = hottest[row_index];
+                    row.col(|ui| {
+                        ui.monospace(cfg::describe_word(
+                            context.memory(),
+                            context.debug_info(),
+                            addr,
+                        ));
+                    });
+                    for count in [
+                        counts.executions,
+                        counts.reads,
+                        counts.writes,
+                        counts.total(),
+                    ] {
+                        row.col(|ui| {
+                            ui.monospace(count.to_string());
+                        });
+                    }
+                });
+            });
+    }
+}
+
+/// The background of a word used `total` times, when the most used word was used `max` times.
+pub fn heat_color(total: u64, max: u64) -> egui::Color32 {
+    let heat = total as f32 / max.max(1) as f32;
+    egui::Color32::from_rgb(230, 80, 20).linear_multiply(0.15 + 0.6 * heat)
+}
@@ -0,0 +1,127 @@
+use eframe::egui;
+use simplez_assembler::format::{FormatOptions, MnemonicCase};
+
+use crate::highlighter;
+
+/// User preferences, edited in the settings window. The syntax highlighting theme is kept in
+/// egui's memory by [`highlighter::CodeTheme::store_in_memory`] instead.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub dark_mode: bool,
+    /// Size of the code editor's font.
+    pub font_size: f32,
+    /// The Tab key indents code up to the next multiple of this many columns.
+    pub tab_width: usize,
+    /// Whether the Format button writes mnemonics and directives in lower case.
+    pub lowercase_mnemonics: bool,
+    pub memory_view: MemoryViewSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            dark_mode: true,
+            font_size: 16.,
+            tab_width: 8,
+            lowercase_mnemonics: false,
+            memory_view: Default::default(),
+        }
+    }
+}
+
+/// What the memory table shows.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MemoryViewSettings {
+    /// Show the column that disassembles each word.
+    pub show_instructions: bool,
+    /// Color the words that were modified most recently.
+    pub highlight_modifications: bool,
+    /// Hide the words the assembler didn't touch, unless the program has written to them.
+    pub only_assembled_words: bool,
+    /// Show the column that counts how often each word was used, colored by how hot it is.
+    pub show_heat_map: bool,
+}
+
+impl Default for MemoryViewSettings {
+    fn default() -> Self {
+        Self {
+            show_instructions: true,
+            highlight_modifications: true,
+            only_assembled_words: false,
+            show_heat_map: true,
+        }
+    }
+}
+
+impl Settings {
+    pub fn code_font(&self) -> egui::FontId {
+        egui::FontId::monospace(self.font_size)
+    }
+
+    pub fn visuals(&self) -> egui::Visuals {
+        if self.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        }
+    }
+
+    /// How the Format button formats programs, aligning columns to the tab width.
+    pub fn format_options(&self) -> FormatOptions {
+        FormatOptions {
+            tab_width: self.tab_width,
+            case: if self.lowercase_mnemonics {
+                MnemonicCase::Lower
+            } else {
+                MnemonicCase::Upper
+            },
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Theme");
+        let mut theme = highlighter::CodeTheme::from_memory(ui.ctx());
+        theme.ui(ui);
+        theme.store_in_memory(ui.ctx());
+        self.dark_mode = ui.ctx().style().visuals.dark_mode;
+
+        ui.separator();
+        ui.heading("Editor");
+        egui::Grid::new("editor_settings").show(ui, |ui| {
+            ui.label("Font size:");
+            ui.add(egui::Slider::new(&mut self.font_size, 8. ..=32.).suffix(" pt"));
+            ui.end_row();
+
+            ui.label("Tab width:");
+            ui.add(egui::DragValue::new(&mut self.tab_width).clamp_range(1..=16))
+                .on_hover_text("How many columns the Tab key indents by");
+            ui.end_row();
+        });
+        ui.checkbox(
+            &mut self.lowercase_mnemonics,
+            "Format mnemonics in lower case",
+        );
+
+        ui.separator();
+        ui.heading("Memory");
+        let memory_view = &mut self.memory_view;
+        ui.checkbox(
+            &mut memory_view.show_instructions,
+            "Show the Instruction column",
+        );
+        ui.checkbox(
+            &mut memory_view.highlight_modifications,
+            "Highlight recently modified words",
+        );
+        ui.checkbox(
+            &mut memory_view.only_assembled_words,
+            "Only show words used by the program",
+        );
+        ui.checkbox(
+            &mut memory_view.show_heat_map,
+            "Show how often each word is used",
+        );
+    }
+}
@@ -0,0 +1,19 @@
+//! Share links on the web, where the app has a URL to put programs in. See
+//! [`simplez_common::share`] for how programs are encoded.
+
+use simplez_common::share;
+
+/// Returns the program in the fragment of the page's URL, if any, and removes it from the URL so
+/// that reloading the page doesn't open it again.
+pub fn take_shared_program() -> Option<Result<String, String>> {
+    let location = web_sys::window()?.location();
+    let program = share::program_from_fragment(&location.hash().ok()?)?;
+    let _ = location.set_hash("");
+    Some(program.map_err(|err| err.to_string()))
+}
+
+/// Returns a link to this page that opens `program`.
+pub fn link(program: &str) -> Option<String> {
+    let page = web_sys::window()?.location().href().ok()?;
+    Some(share::share_link(&page, program))
+}
@@ -0,0 +1,82 @@
+use eframe::egui;
+use simplez_interpreter::snapshot::Snapshot;
+
+use crate::document::Document;
+use crate::file;
+
+/// A snapshot the user saved in one of the document's slots.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct NamedSnapshot {
+    pub name: String,
+    pub snapshot: Snapshot,
+}
+
+/// The snapshots window, for saving the machine of a document to named slots and restoring it.
+#[derive(Default)]
+pub struct SnapshotsView {
+    /// The name of the next snapshot to save.
+    name: String,
+}
+
+impl SnapshotsView {
+    /// Returns an error if exporting a snapshot failed.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        doc: &mut Document,
+        file_dialogs: &file::FileDialogs,
+    ) -> Result<(), String> {
+        let mut result = Ok(());
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.name).hint_text("Name"));
+            let name = self.name.trim();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Save the machine to a slot, replacing any with the same name")
+                .clicked()
+            {
+                doc.add_snapshot(name.to_owned(), doc.context.snapshot());
+                self.name.clear();
+            }
+            if ui.button("Import…").clicked() {
+                file_dialogs.open_snapshot(ui.ctx());
+            }
+        });
+        ui.separator();
+
+        if doc.snapshots.is_empty() {
+            ui.label("No snapshots saved.");
+        }
+        let mut restored = None;
+        let mut deleted = None;
+        egui::Grid::new("snapshots").striped(true).show(ui, |ui| {
+            for (index, slot) in doc.snapshots.iter().enumerate() {
+                ui.label(&slot.name);
+                ui.monospace(format!(
+                    "PC {}, {} steps",
+                    slot.snapshot.pc, slot.snapshot.instructions_executed
+                ));
+                if ui.button("Restore").clicked() {
+                    restored = Some(index);
+                }
+                if ui.button("Export…").clicked() {
+                    let name = format!("{}.json", slot.name);
+                    if let Some(path) = file::pick_export_path(&name, "Simplez snapshot", "json") {
+                        result = file::save(&path, &slot.snapshot.to_json());
+                    }
+                }
+                if ui.button("Delete").clicked() {
+                    deleted = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = restored {
+            doc.restore_snapshot(doc.snapshots[index].snapshot.clone());
+        }
+        if let Some(index) = deleted {
+            doc.snapshots.remove(index);
+        }
+        result
+    }
+}
@@ -0,0 +1,136 @@
+use eframe::egui;
+use simplez_interpreter::trace::{TraceEntry, Tracer};
+use simplez_interpreter::ExecutionContext;
+
+use crate::file;
+use crate::word::WordFormat;
+
+/// The trace window, listing the steps recorded by the tracer of the execution context.
+#[derive(Default)]
+pub struct TraceView {
+    /// An address such as `/12` or a mnemonic such as `LD`. Only matching steps are shown.
+    filter: String,
+}
+
+impl TraceView {
+    /// Shows the recorded steps with their values in `format`. Returns an error if exporting
+    /// them failed.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        context: &mut ExecutionContext,
+        format: WordFormat,
+    ) -> Result<(), String> {
+        let mut result = Ok(());
+        ui.horizontal(|ui| {
+            let mut recording = context.tracer.is_some();
+            if ui
+                .checkbox(&mut recording, "Record")
+                .on_hover_text("Record every instruction executed from now on")
+                .changed()
+            {
+                context.tracer = recording.then(Tracer::default);
+            }
+            let tracer = match &mut context.tracer {
+                Some(tracer) => tracer,
+                None => return,
+            };
+            if ui.button("Clear").clicked() {
+                tracer.clear();
+            }
+            if ui.button("Export CSV").clicked() {
+                result = export("trace.csv", "CSV", "csv", &tracer.to_csv());
+            }
+            if ui.button("Export JSON Lines").clicked() {
+                result = export(
+                    "trace.jsonl",
+                    "JSON Lines",
+                    "jsonl",
+                    &tracer.to_json_lines(),
+                );
+            }
+        });
+        ui.add(
+            egui::TextEdit::singleline(&mut self.filter)
+                .hint_text("Filter by address (/12) or instruction (LD)"),
+        );
+
+        let tracer = match &context.tracer {
+            Some(tracer) => tracer,
+            None => {
+                ui.label("Not recording.");
+                return result;
+            }
+        };
+        let entries: Vec<&TraceEntry> = tracer
+            .entries()
+            .iter()
+            .filter(|entry| entry.matches(&self.filter))
+            .collect();
+        if tracer.dropped() > 0 {
+            ui.label(format!(
+                "{} steps recorded, the {} oldest were forgotten",
+                tracer.entries().len(),
+                tracer.dropped()
+            ));
+        } else {
+            ui.label(format!("{} steps recorded", tracer.entries().len()));
+        }
+        ui.separator();
+
+        let heading_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui_extras::TableBuilder::new(ui)
+            .striped(true)
+            .column(egui_extras::Size::exact(50.))
+            .column(egui_extras::Size::exact(40.))
+            .column(egui_extras::Size::exact(70.))
+            .column(egui_extras::Size::exact(110.))
+            .column(egui_extras::Size::remainder())
+            .header(heading_height, |mut header| {
+                for title in ["Step", "PC", "Instruction", "ACC", "Write"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(16., entries.len(), |row_index, mut row| {
+                    let entry = entries[row_index];
+                    row.col(|ui| {
+                        ui.monospace(entry.step.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.monospace(entry.pc.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.monospace(entry.instruction.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!(
+                            "{} → {}",
+                            format.format(entry.acc_before),
+                            format.format(entry.acc_after)
+                        ));
+                    });
+                    row.col(|ui| {
+                        if let Some(write) = entry.write {
+                            ui.monospace(format!(
+                                "[{}] {} → {}",
+                                write.address,
+                                format.format(write.before),
+                                format.format(write.after)
+                            ));
+                        }
+                    });
+                });
+            });
+        result
+    }
+}
+
+fn export(name: &str, filter_name: &str, extension: &str, contents: &str) -> Result<(), String> {
+    match file::pick_export_path(name, filter_name, extension) {
+        Some(path) => file::save(&path, contents),
+        None => Ok(()),
+    }
+}
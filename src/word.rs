@@ -0,0 +1,255 @@
+use eframe::egui;
+use twelve_bit::u12::U12;
+
+/// A way of displaying the contents of a word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum WordFormat {
+    Decimal,
+    /// Decimal, interpreting the word as a two's complement number.
+    SignedDecimal,
+    Octal,
+    Hexadecimal,
+    Binary,
+    /// The ASCII character the word encodes, if any.
+    Ascii,
+}
+
+impl WordFormat {
+    pub const ALL: [Self; 6] = [
+        Self::Decimal,
+        Self::SignedDecimal,
+        Self::Octal,
+        Self::Hexadecimal,
+        Self::Binary,
+        Self::Ascii,
+    ];
+    /// The formats that make sense for addresses, which are neither negative nor characters.
+    pub const ADDRESS: [Self; 4] = [Self::Decimal, Self::Octal, Self::Hexadecimal, Self::Binary];
+
+    /// A short name to show in column headers.
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Self::Decimal => "DEC",
+            Self::SignedDecimal => "±DEC",
+            Self::Octal => "OCT",
+            Self::Hexadecimal => "HEX",
+            Self::Binary => "BIN",
+            Self::Ascii => "CHAR",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Decimal => "Decimal",
+            Self::SignedDecimal => "Signed decimal",
+            Self::Octal => "Octal",
+            Self::Hexadecimal => "Hexadecimal",
+            Self::Binary => "Binary",
+            Self::Ascii => "ASCII character",
+        }
+    }
+
+    pub fn format(self, word: U12) -> String {
+        let value = u16::from(word);
+        match self {
+            Self::Decimal => value.to_string(),
+            Self::SignedDecimal => signed(word).to_string(),
+            Self::Octal => format!("{:04o}", value),
+            Self::Hexadecimal => format!("{:03X}", value),
+            Self::Binary => format!("{:012b}", value),
+            Self::Ascii => match ascii(word) {
+                Some(c) => format!("'{}'", c),
+                None => "·".to_owned(),
+            },
+        }
+    }
+
+    /// Formats `word` in a way [`parse_word`] understands, to prefill the text field of a cell
+    /// displayed in this format.
+    pub fn format_editable(self, word: U12) -> String {
+        let value = u16::from(word);
+        match self {
+            Self::Octal => format!("0o{:o}", value),
+            Self::Hexadecimal => format!("0x{:X}", value),
+            Self::Binary => format!("0b{:012b}", value),
+            Self::Ascii if ascii(word).is_some() => self.format(word),
+            Self::SignedDecimal => self.format(word),
+            Self::Decimal | Self::Ascii => value.to_string(),
+        }
+    }
+
+    /// Shows `title` as a column header that can be clicked to select the format.
+    pub fn header_ui(&mut self, ui: &mut egui::Ui, title: &str) {
+        self.header_ui_among(ui, title, &Self::ALL);
+    }
+
+    /// Like [`Self::header_ui`], but only offering `formats`. Switches to the first of them if the
+    /// current format isn't one, as it can be in settings saved by older versions.
+    pub fn header_ui_among(&mut self, ui: &mut egui::Ui, title: &str, formats: &[Self]) {
+        if !formats.contains(self) {
+            *self = formats[0];
+        }
+        ui.menu_button(
+            egui::RichText::new(format!("{} ({})", title, self.abbreviation())).heading(),
+            |ui| {
+                for &format in formats {
+                    if ui.selectable_value(self, format, format.name()).clicked() {
+                        ui.close_menu();
+                    }
+                }
+            },
+        );
+    }
+}
+
+fn signed(word: U12) -> i16 {
+    let value = u16::from(word) as i16;
+    if value >= 2048 {
+        value - 4096
+    } else {
+        value
+    }
+}
+
+fn ascii(word: U12) -> Option<char> {
+    u8::try_from(u16::from(word))
+        .ok()
+        .map(char::from)
+        .filter(|c| c.is_ascii_graphic() || *c == ' ')
+}
+
+/// Parses a word typed in by the user. It can be written in decimal (`42`, or `-1` for two's
+/// complement), octal (`0o52`), hexadecimal (`0x2A`), binary (`0b101010`), as a quoted ASCII
+/// character (`'*'`) or as an instruction (`LD /12`).
+pub fn parse_word(text: &str) -> Result<U12, String> {
+    let text = text.trim();
+    if let Some(quoted) = text.strip_prefix('\'') {
+        let mut chars = quoted.strip_suffix('\'').unwrap_or(quoted).chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii() => Ok(U12::from_u16(c as u16)),
+            _ => Err(format!("{} is not a single ASCII character", text)),
+        };
+    }
+    if !text.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        return simplez_assembler::assemble_instruction(text)
+            .map_err(|err| format!("{:?}", err.kind));
+    }
+
+    let lowercase = text.to_lowercase();
+    let (digits, radix) = if let Some(digits) = lowercase.strip_prefix("0b") {
+        (digits, 2)
+    } else if let Some(digits) = lowercase.strip_prefix("0o") {
+        (digits, 8)
+    } else if let Some(digits) = lowercase.strip_prefix("0x") {
+        (digits, 16)
+    } else {
+        (lowercase.as_str(), 10)
+    };
+    let value = i32::from_str_radix(digits, radix).map_err(|err| err.to_string())?;
+
+    match value {
+        0..=4095 => Ok(U12::from_u16(value as u16)),
+        -2048..=-1 if radix == 10 => Ok(U12::from_u16((4096 + value) as u16)),
+        _ => Err(format!("{} does not fit in 12 bits", text)),
+    }
+}
+
+/// A word being edited in one of the state panel's cells.
+pub struct WordEditor {
+    cell: egui::Id,
+    text: String,
+    error: Option<String>,
+    request_focus: bool,
+}
+
+/// Shows `label` in a cell that turns into a text field when clicked, prefilled with
+/// `initial_text`. Returns the value typed in once the user presses enter, as long as it parses
+/// and is not greater than `max`. Clicking elsewhere or pressing escape cancels the edit.
+pub fn editable_word(
+    ui: &mut egui::Ui,
+    editor: &mut Option<WordEditor>,
+    cell: egui::Id,
+    max: u16,
+    initial_text: impl FnOnce() -> String,
+    label: egui::RichText,
+) -> Option<U12> {
+    let edit = match editor {
+        Some(edit) if edit.cell == cell => edit,
+        _ => {
+            if ui
+                .add(egui::Label::new(label).sense(egui::Sense::click()))
+                .on_hover_text("Click to edit")
+                .clicked()
+            {
+                *editor = Some(WordEditor {
+                    cell,
+                    text: initial_text(),
+                    error: None,
+                    request_focus: true,
+                });
+            }
+            return None;
+        }
+    };
+
+    let mut text_edit = egui::TextEdit::singleline(&mut edit.text)
+        .font(egui::TextStyle::Monospace)
+        .desired_width(f32::INFINITY);
+    if edit.error.is_some() {
+        text_edit = text_edit.text_color(ui.visuals().error_fg_color);
+    }
+    let mut response = ui.add(text_edit);
+    if std::mem::take(&mut edit.request_focus) {
+        response.request_focus();
+    }
+    if let Some(error) = &edit.error {
+        response = response.on_hover_text(error);
+    }
+    if response.changed() {
+        edit.error = None;
+    }
+    if !response.lost_focus() {
+        return None;
+    }
+
+    if !ui.input().key_pressed(egui::Key::Enter) {
+        *editor = None;
+        return None;
+    }
+    match parse_word(&edit.text) {
+        Ok(value) if u16::from(value) <= max => {
+            *editor = None;
+            Some(value)
+        }
+        Ok(value) => {
+            edit.error = Some(format!("{} is greater than {}", u16::from(value), max));
+            edit.request_focus = true;
+            None
+        }
+        Err(error) => {
+            edit.error = Some(error);
+            edit.request_focus = true;
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn parse_words() {
+    let parse = |text| parse_word(text).map(u16::from);
+    assert_eq!(parse("42"), Ok(42));
+    assert_eq!(parse(" -1 "), Ok(4095));
+    assert_eq!(parse("0o52"), Ok(0o52));
+    assert_eq!(parse("0x2a"), Ok(0x2A));
+    assert_eq!(parse("0b101010"), Ok(42));
+    assert_eq!(parse("'*'"), Ok(42));
+    assert_eq!(parse("LD /12"), Ok(1 << 9 | 12));
+    assert_eq!(parse("halt"), Ok(7 << 9));
+
+    for wrong in [
+        "4096", "-2049", "0x1000", "0o10000", "-0x1", "'ab'", "FOO /1",
+    ] {
+        assert!(parse(wrong).is_err(), "{} parsed", wrong);
+    }
+}